@@ -0,0 +1,155 @@
+//kanban board for the tui, with a column for each workflow state.
+//tasks are moved between columns with the keyboard and every move is saved straight away, like `vayu move`.
+
+use crate::clock;
+use crate::config;
+use crate::deps;
+use crate::journal;
+use crate::store::{Access, Store, Task};
+use crate::urgency;
+use crate::workflow;
+use chrono::{DateTime, Duration, Local};
+use ratatui::{prelude::*, widgets::*};
+use std::io;
+use std::path::Path;
+
+#[derive(Default)]
+pub struct Board {
+    pub column: usize,
+    pub row: usize,
+    //what the last move did, shown under the board
+    pub message: String,
+}
+
+//indexes of the tasks in each column. pending columns hold the tasks that aren't waiting, most urgent first.
+//the done column only has the last week's tasks, most recently finished first
+pub fn columns(tasks: &[Task], now: DateTime<Local>) -> Vec<Vec<usize>> {
+    let today = now.format("%Y-%m-%d").to_string();
+    let week_ago = now - Duration::days(7);
+    config::states().iter().map(|state| {
+        let mut column: Vec<usize> = (0..tasks.len()).filter(|&i| {
+            let task = &tasks[i];
+            workflow::state(task) == state
+                && !task.is_waiting(&today)
+                && (!task.done || task.completed_at.is_some_and(|at| at >= week_ago))
+        }).collect();
+        if state == workflow::done_state() {
+            column.sort_by_key(|&i| std::cmp::Reverse(tasks[i].completed_at));
+        }
+        else {
+            let score = |i: usize| urgency::urgency(tasks, &tasks[i], now);
+            column.sort_by(|&a, &b| score(b).total_cmp(&score(a)).then_with(|| tasks[a].due.cmp(&tasks[b].due)));
+        }
+        column
+    }).collect()
+}
+
+impl Board {
+    //index of the selected task, if its column has any
+    pub fn selected(&self, columns: &[Vec<usize>]) -> Option<usize> {
+        columns.get(self.column)?.get(self.row).copied()
+    }
+
+    pub fn left(&mut self, columns: &[Vec<usize>]) {
+        self.column = self.column.saturating_sub(1);
+        self.clamp(columns);
+    }
+
+    pub fn right(&mut self, columns: &[Vec<usize>]) {
+        self.column = (self.column + 1).min(columns.len() - 1);
+        self.clamp(columns);
+    }
+
+    pub fn up(&mut self) {
+        self.row = self.row.saturating_sub(1);
+    }
+
+    pub fn down(&mut self, columns: &[Vec<usize>]) {
+        self.row += 1;
+        self.clamp(columns);
+    }
+
+    fn clamp(&mut self, columns: &[Vec<usize>]) {
+        self.row = self.row.min(columns[self.column].len().saturating_sub(1));
+    }
+}
+
+pub fn render(frame: &mut Frame, area: Rect, tasks: &[Task], board: &Board) {
+    let columns = columns(tasks, clock::now());
+    let layout = Layout::new(Direction::Vertical, [Constraint::Min(0), Constraint::Length(1)]).split(area);
+    let constraints: Vec<Constraint> = columns.iter().map(|_| Constraint::Ratio(1, columns.len() as u32)).collect();
+    let column_areas = Layout::new(Direction::Horizontal, constraints).split(layout[0]);
+    for (c, (state, column)) in config::states().iter().zip(&columns).enumerate() {
+        let items: Vec<ListItem> = column.iter().map(|&i| {
+            let task = &tasks[i];
+            let item = ListItem::new(format!("{} {}", task.id, task.labelled_description()));
+            //blocked tasks are greyed out like in the task list
+            if deps::is_blocked(tasks, task) {
+                item.style(Style::default().fg(Color::DarkGray))
+            }
+            else {
+                item
+            }
+        }).collect();
+        let title_style = if c == board.column { Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD) } else { Style::default() };
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(Span::styled(format!("{} ({})", state, column.len()), title_style)))
+            .style(Style::default().fg(Color::White).bg(Color::Black))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .highlight_symbol(">>");
+        let mut state = ListState::default();
+        if c == board.column && !column.is_empty() {
+            state.select(Some(board.row));
+        }
+        frame.render_stateful_widget(list, column_areas[c], &mut state);
+    }
+    frame.render_widget(Paragraph::new(board.message.clone()).style(Style::default().fg(Color::Green).bg(Color::Black)), layout[1]);
+}
+
+//moves the selected task one state to the left (step -1) or right (step 1) and saves it.
+//the store is reloaded under the lock first, so the move applies to what's on disk now.
+//`tasks` is replaced with the saved list and the selection follows the task.
+pub fn move_selected(dir: &Path, tasks: &mut Vec<Task>, board: &mut Board, step: isize) -> io::Result<()> {
+    let now = clock::now();
+    let Some(index) = board.selected(&columns(tasks, now)) else {
+        return Ok(());
+    };
+    let states = config::states();
+    let Some(target) = board.column.checked_add_signed(step).filter(|&c| c < states.len()) else {
+        return Ok(());
+    };
+    let uuid = tasks[index].uuid;
+    let state = states[target].as_str();
+
+    //loading can't print while the board is on screen, so what it warns about goes in the status line
+    let mut store = Store::load_quietly(dir, Access::Write)?;
+    let before = journal::snapshot(&store);
+    let next_id = store.next_task_id();
+    let Some(task) = store.tasks.iter_mut().find(|t| t.uuid == uuid) else {
+        board.message = with_warnings(format!("task {} was removed by another command", tasks[index].id), &store.warnings);
+        *tasks = store.tasks.clone();
+        return Ok(());
+    };
+    let id = task.id;
+    //subtasks are left open, there's no asking about them here
+    let message = if state == workflow::done_state() {
+        workflow::complete(&mut store.tasks, next_id, &[id], now).join(", ")
+    }
+    else {
+        workflow::enter(task, state, now);
+        format!("task {} moved to {}", id, state)
+    };
+    board.message = with_warnings(message, &store.warnings);
+    journal::save_and_record(&mut store, &before, "move")?;
+    *tasks = store.tasks.clone();
+
+    let columns = columns(tasks, now);
+    board.column = target;
+    board.row = columns[target].iter().position(|&i| tasks[i].uuid == uuid).unwrap_or(0);
+    Ok(())
+}
+
+//the status line for a move, followed by anything loading the store warned about
+fn with_warnings(message: String, warnings: &[String]) -> String {
+    std::iter::once(message).chain(warnings.iter().cloned()).collect::<Vec<_>>().join(". ")
+}
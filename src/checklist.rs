@@ -0,0 +1,90 @@
+//checklists: steps inside a task that don't need ids of their own, ex: the steps of a release.
+//items are numbered from 1 in the order they were added.
+
+use crate::store::{self, CheckItem, Task};
+use crossterm::style::Stylize;
+
+//(ticked, total) items, or None if the task has no checklist
+pub fn progress(task: &Task) -> Option<(usize, usize)> {
+    if task.checklist.is_empty() {
+        return None;
+    }
+    Some((task.checklist.iter().filter(|item| item.done).count(), task.checklist.len()))
+}
+
+pub fn add(tasks: &mut [Task], key: &str, text: &str) {
+    let index = match store::find_task(tasks, key) {
+        Ok(index) => index,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+    let text = text.trim();
+    if text.is_empty() {
+        println!("checklist item can't be empty");
+        return;
+    }
+    let task = &mut tasks[index];
+    task.checklist.push(CheckItem { text: text.to_string(), done: false });
+    println!("added item {} to task {}", task.checklist.len(), task.id);
+}
+
+//ticks the numbered items, or unticks them when `done` is false
+pub fn tick(tasks: &mut [Task], key: &str, items: &[String], done: bool) {
+    let index = match store::find_task(tasks, key) {
+        Ok(index) => index,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+    let task = &mut tasks[index];
+    //every number is checked first so a typo doesn't leave half of them ticked
+    let mut numbers = Vec::new();
+    for item in items {
+        match item.parse::<usize>() {
+            Ok(n) if (1..=task.checklist.len()).contains(&n) => numbers.push(n),
+            _ if task.checklist.is_empty() => {
+                println!("task {} has no checklist. add items with vayu check add {} \"step\"", task.id, task.id);
+                return;
+            },
+            _ => {
+                println!("invalid item \"{}\". task {} has items 1 to {}", item, task.id, task.checklist.len());
+                return;
+            }
+        }
+    }
+    for n in numbers {
+        task.checklist[n - 1].done = done;
+    }
+    let (ticked, total) = progress(task).unwrap_or((0, 0));
+    println!("task {} checklist {}/{}", task.id, ticked, total);
+}
+
+pub fn show(tasks: &[Task], key: &str) {
+    let task = match store::find_task(tasks, key) {
+        Ok(index) => &tasks[index],
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+    if task.checklist.is_empty() {
+        println!("task {} has no checklist", task.id);
+        return;
+    }
+    print(task);
+}
+
+//the numbered items, ticked ones dimmed
+pub fn print(task: &Task) {
+    for (n, item) in task.checklist.iter().enumerate() {
+        if item.done {
+            println!("{}", format!("{:>2}. [x] {}", n + 1, item.text).dim());
+        }
+        else {
+            println!("{:>2}. [ ] {}", n + 1, item.text);
+        }
+    }
+}
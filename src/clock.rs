@@ -0,0 +1,120 @@
+//the current time, for everything that depends on what day it is.
+//VAYU_NOW moves vayu to another moment for debugging, ex: VAYU_NOW="2024-06-12 09:00" vayu list.
+//the clock keeps running from there, so timers and the pomodoro still count down.
+
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, TimeZone};
+use std::env;
+use std::sync::OnceLock;
+
+pub const NOW_VAR: &str = "VAYU_NOW";
+
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Local>;
+}
+
+//the system time
+pub struct System;
+
+impl Clock for System {
+    fn now(&self) -> DateTime<Local> {
+        Local::now()
+    }
+}
+
+//the system time moved by a fixed amount
+pub struct Shifted(pub Duration);
+
+impl Clock for Shifted {
+    fn now(&self) -> DateTime<Local> {
+        Local::now() + self.0
+    }
+}
+
+static CLOCK: OnceLock<Box<dyn Clock>> = OnceLock::new();
+
+//the clock VAYU_NOW asks for, or the system one
+pub fn from_env() -> Result<Box<dyn Clock>, String> {
+    match env::var(NOW_VAR) {
+        Ok(text) if !text.trim().is_empty() => {
+            let at = parse(&text).map_err(|e| format!("{}: {}", NOW_VAR, e))?;
+            Ok(Box::new(Shifted(at - Local::now())))
+        },
+        _ => Ok(Box::new(System)),
+    }
+}
+
+//a moment as YYYY-MM-DD, YYYY-MM-DD HH:MM[:SS] (or with a T) in the local zone, or RFC 3339 with an offset
+pub fn parse(text: &str) -> Result<DateTime<Local>, String> {
+    let text = text.trim();
+    if let Ok(at) = DateTime::parse_from_rfc3339(text) {
+        return Ok(at.with_timezone(&Local));
+    }
+    let naive = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"].iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+        .or_else(|| NaiveDate::parse_from_str(text, "%Y-%m-%d").ok().and_then(|date| date.and_hms_opt(0, 0, 0)))
+        .ok_or_else(|| format!("invalid time \"{}\". use YYYY-MM-DD or YYYY-MM-DD HH:MM", text))?;
+    Local.from_local_datetime(&naive).earliest()
+        .ok_or_else(|| format!("{} doesn't exist in the local time zone", naive))
+}
+
+//sets the clock for the rest of the run. only the first call counts
+pub fn install(clock: Box<dyn Clock>) {
+    CLOCK.get_or_init(|| clock);
+}
+
+//the moment tests on this thread see as now, so they don't race each other over the global clock
+#[cfg(test)]
+thread_local! {
+    static TEST_NOW: std::cell::Cell<Option<DateTime<Local>>> = const { std::cell::Cell::new(None) };
+}
+
+#[cfg(test)]
+pub fn set_for_test(at: DateTime<Local>) {
+    TEST_NOW.with(|now| now.set(Some(at)));
+}
+
+//every part of vayu asks the time here, so VAYU_NOW (and the tests) move all of it at once
+pub fn now() -> DateTime<Local> {
+    #[cfg(test)]
+    if let Some(at) = TEST_NOW.with(|now| now.get()) {
+        return at;
+    }
+    CLOCK.get_or_init(|| Box::new(System)).now()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Timelike;
+
+    #[test]
+    fn parses_vayu_now() {
+        let cases = [
+            ("2024-06-12", Some("2024-06-12 00:00:00")),
+            ("2024-06-12 09:30", Some("2024-06-12 09:30:00")),
+            (" 2024-06-12T09:30:15 ", Some("2024-06-12 09:30:15")),
+            ("2024-06-12 09:30:15", Some("2024-06-12 09:30:15")),
+            ("tomorrow", None),
+            ("2024-06-31", None),
+            ("2024-06-12 25:00", None),
+        ];
+        for (input, expected) in cases {
+            let parsed = parse(input).ok().map(|at| at.format("%Y-%m-%d %H:%M:%S").to_string());
+            assert_eq!(parsed.as_deref(), expected, "parse({:?})", input);
+        }
+    }
+
+    #[test]
+    fn parses_offsets() {
+        let at = parse("2024-06-12T09:30:00+00:00").unwrap();
+        assert_eq!(at.naive_utc().hour(), 9);
+    }
+
+    #[test]
+    fn shifted_clock_keeps_running() {
+        let target = parse("2001-02-03 04:05").unwrap();
+        let clock = Shifted(target - Local::now());
+        let now = clock.now();
+        assert!(now >= target && now - target < Duration::seconds(5));
+    }
+}
@@ -0,0 +1,336 @@
+//settings from config.toml in the data directory, so a project's .vayu/ can have its own.
+//it declares user defined attributes (UDAs), extra task fields like an estimate or a ticket:
+//
+//  [uda.estimate]
+//  type = "duration"
+//  default = "1h"
+//
+//  [uda.customer]
+//  type = "enum"
+//  values = ["acme", "globex"]
+//
+//UDA values are kept with the task's other fields in the store, so they round trip and show up in exports.
+//
+//and the workflow states a task moves through with `vayu move`. the first is where new tasks start,
+//the last one means done:
+//
+//  [workflow]
+//  states = ["todo", "doing", "review", "done"]
+
+use crate::clock;
+use crate::dates;
+use crate::store::{self, Task};
+use serde::Deserialize;
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::OnceLock;
+
+pub const CONFIG_FILE: &str = "config.toml";
+
+//names vayu already uses for task fields or in queries
+const RESERVED: [&str; 34] = [
+    "id", "uuid", "description", "desc", "due", "due_at", "due_tz", "tz", "wait", "scheduled", "sched",
+    "done", "status", "completed_at", "created_at", "priority", "pri", "tags", "contexts", "parent",
+    "depends", "dep", "dependencies", "recur", "intervals", "annotations", "ready", "blocked", "waiting",
+    "active", "urgency", "state", "transitions", "checklist",
+];
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    pub uda: BTreeMap<String, Uda>,
+    #[serde(default)]
+    pub workflow: Workflow,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Workflow {
+    pub states: Vec<String>,
+}
+
+impl Default for Workflow {
+    fn default() -> Workflow {
+        Workflow { states: vec!["todo".to_string(), "doing".to_string(), "done".to_string()] }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Uda {
+    #[serde(rename = "type")]
+    pub kind: UdaType,
+    //the allowed values of an enum
+    #[serde(default)]
+    pub values: Vec<String>,
+    //set on new tasks that don't give a value
+    pub default: Option<String>,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum UdaType {
+    String,
+    Number,
+    Date,
+    Duration,
+    Enum,
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+//reads config.toml from the data directory. a missing file is an empty config
+pub fn load(dir: &Path) -> Result<(), String> {
+    let path = dir.join(CONFIG_FILE);
+    let config = match fs::read_to_string(&path) {
+        Ok(text) => parse(&text).map_err(|e| format!("{}: {}", path.display(), e))?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Config::default(),
+        Err(e) => return Err(format!("{}: {}", path.display(), e)),
+    };
+    CONFIG.get_or_init(|| config);
+    Ok(())
+}
+
+//reads and checks the text of a config.toml
+fn parse(text: &str) -> Result<Config, String> {
+    let config = toml::from_str::<Config>(text).map_err(|e| e.to_string())?;
+    for (name, uda) in &config.uda {
+        if RESERVED.contains(&name.as_str()) || !store::valid_label(name) || name.contains('.') {
+            return Err(format!("\"{}\" can't be used as an attribute name", name));
+        }
+        if uda.kind == UdaType::Enum && uda.values.is_empty() {
+            return Err(format!("enum attribute \"{}\" needs a list of values", name));
+        }
+        if let Some(default) = &uda.default {
+            parse_value(uda, default).map_err(|e| format!("default for \"{}\": {}", name, e))?;
+        }
+    }
+    let states = &config.workflow.states;
+    if states.len() < 2 {
+        return Err("the workflow needs at least two states, one to start in and one for done".to_string());
+    }
+    for (i, state) in states.iter().enumerate() {
+        if !store::valid_label(state) {
+            return Err(format!("\"{}\" can't be used as a workflow state", state));
+        }
+        if states[..i].iter().any(|s| s.eq_ignore_ascii_case(state)) {
+            return Err(format!("workflow state \"{}\" is listed twice", state));
+        }
+    }
+    Ok(config)
+}
+
+fn config() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+pub fn uda(name: &str) -> Option<&'static Uda> {
+    config().uda.get(name)
+}
+
+pub fn udas() -> impl Iterator<Item = (&'static String, &'static Uda)> {
+    config().uda.iter()
+}
+
+//workflow states in order, always at least two
+pub fn states() -> &'static [String] {
+    &config().workflow.states
+}
+
+//checks a value as typed and turns it into the form kept in the store
+pub fn parse_value(uda: &Uda, value: &str) -> Result<Value, String> {
+    let value = value.trim();
+    match uda.kind {
+        UdaType::String => Ok(Value::String(value.to_string())),
+        UdaType::Number => {
+            if let Ok(n) = value.parse::<i64>() {
+                return Ok(Value::from(n));
+            }
+            value.parse::<f64>().ok()
+                .and_then(serde_json::Number::from_f64)
+                .map(Value::Number)
+                .ok_or_else(|| format!("\"{}\" isn't a number", value))
+        },
+        UdaType::Date => dates::parse_date(value, clock::now().date_naive())
+            .map(|date| Value::String(date.format("%Y-%m-%d").to_string())),
+        UdaType::Duration => parse_minutes(value)
+            .map(|minutes| Value::String(format_minutes(minutes))),
+        UdaType::Enum => uda.values.iter()
+            .find(|allowed| allowed.eq_ignore_ascii_case(value))
+            .map(|allowed| Value::String(allowed.clone()))
+            .ok_or_else(|| format!("\"{}\" isn't one of {}", value, uda.values.join(", "))),
+    }
+}
+
+//durations are parsed like everywhere else in vayu, so 1d is 24 hours
+fn parse_minutes(text: &str) -> Result<i64, String> {
+    dates::parse_duration(text).map(|duration| duration.num_minutes())
+}
+
+fn format_minutes(minutes: i64) -> String {
+    match (minutes / 60, minutes % 60) {
+        (0, m) => format!("{}m", m),
+        (h, 0) => format!("{}h", h),
+        (h, m) => format!("{}h{}m", h, m),
+    }
+}
+
+//orders two stored values of an attribute. tasks without a value go last
+pub fn compare(uda: &Uda, a: Option<&Value>, b: Option<&Value>) -> Ordering {
+    let number = |value: &Value| match uda.kind {
+        UdaType::Number => value.as_f64(),
+        UdaType::Duration => value.as_str().and_then(|text| parse_minutes(text).ok()).map(|m| m as f64),
+        _ => None,
+    };
+    match (a, b) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(a), Some(b)) => match (number(a), number(b)) {
+            (Some(a), Some(b)) => a.total_cmp(&b),
+            //enums sort in the order their values are listed
+            _ if uda.kind == UdaType::Enum => {
+                let position = |value: &Value| uda.values.iter().position(|v| Some(v.as_str()) == value.as_str());
+                position(a).cmp(&position(b))
+            },
+            _ => a.as_str().unwrap_or("").cmp(b.as_str().unwrap_or("")),
+        },
+    }
+}
+
+//text for a stored value in listings
+pub fn show(value: Option<&Value>) -> String {
+    match value {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Null) | None => String::new(),
+        Some(value) => value.to_string(),
+    }
+}
+
+//sets the defaults of attributes a new task doesn't have yet
+pub fn apply_defaults(task: &mut Task) {
+    for (name, uda) in udas() {
+        if let Some(value) = uda.default.as_deref().and_then(|default| parse_value(uda, default).ok()) {
+            task.extra.entry(name.clone()).or_insert(value);
+        }
+    }
+}
+
+//checks the attribute values a task holds, for loading and `vayu doctor`
+pub fn check_task(task: &Task) -> Result<(), String> {
+    check_values(&config().uda, task)
+}
+
+fn check_values(udas: &BTreeMap<String, Uda>, task: &Task) -> Result<(), String> {
+    for (name, uda) in udas {
+        if let Some(value) = task.extra.get(name) {
+            let text = show(Some(value));
+            match parse_value(uda, &text) {
+                Ok(parsed) if uda.kind != UdaType::Date || parsed == *value => {},
+                _ => return Err(format!("bad value {} for {}", value, name)),
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uda(kind: UdaType, values: &[&str]) -> Uda {
+        Uda { kind, values: values.iter().map(|v| v.to_string()).collect(), default: None }
+    }
+
+    #[test]
+    fn parses_values() {
+        clock::set_for_test(clock::parse("2024-06-12 09:00").unwrap());
+        let customer = ["acme", "globex"];
+        let cases = [
+            (UdaType::String, " OPS-12 ", Ok(Value::from("OPS-12"))),
+            (UdaType::Number, "3", Ok(Value::from(3))),
+            (UdaType::Number, "2.5", Ok(Value::from(2.5))),
+            (UdaType::Number, "three", Err("\"three\" isn't a number")),
+            (UdaType::Date, "2024-07-01", Ok(Value::from("2024-07-01"))),
+            (UdaType::Date, "tomorrow", Ok(Value::from("2024-06-13"))),
+            (UdaType::Duration, "90m", Ok(Value::from("1h30m"))),
+            (UdaType::Duration, "1d", Ok(Value::from("24h"))),
+            (UdaType::Duration, "45m", Ok(Value::from("45m"))),
+            (UdaType::Duration, "2x", Err("invalid duration \"2x\". use something like 90m, 1h30m, 2h, 3d or 1w")),
+            (UdaType::Enum, "ACME", Ok(Value::from("acme"))),
+            (UdaType::Enum, "initech", Err("\"initech\" isn't one of acme, globex")),
+        ];
+        for (kind, input, expected) in cases {
+            let parsed = parse_value(&uda(kind, &customer), input);
+            assert_eq!(parsed, expected.map_err(String::from), "parse_value({:?})", input);
+        }
+        assert!(parse_value(&uda(UdaType::Date, &[]), "someday").is_err());
+    }
+
+    #[test]
+    fn compares_values() {
+        let value = |text: &str| Some(Value::from(text));
+        let cases = [
+            (uda(UdaType::Duration, &[]), value("30m"), value("2h"), Ordering::Less),
+            (uda(UdaType::Duration, &[]), value("1h30m"), value("90m"), Ordering::Equal),
+            (uda(UdaType::Number, &[]), Some(Value::from(10)), Some(Value::from(9.5)), Ordering::Greater),
+            (uda(UdaType::Enum, &["low", "high"]), value("high"), value("low"), Ordering::Greater),
+            (uda(UdaType::String, &[]), value("b"), value("a"), Ordering::Greater),
+            (uda(UdaType::Date, &[]), value("2024-06-01"), value("2024-07-01"), Ordering::Less),
+            //tasks without a value go last
+            (uda(UdaType::Number, &[]), None, Some(Value::from(1)), Ordering::Greater),
+            (uda(UdaType::Number, &[]), None, None, Ordering::Equal),
+        ];
+        for (uda, a, b, expected) in cases {
+            assert_eq!(compare(&uda, a.as_ref(), b.as_ref()), expected, "{:?} against {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn checks_stored_values() {
+        let udas = BTreeMap::from([
+            ("estimate".to_string(), uda(UdaType::Duration, &[])),
+            ("review".to_string(), uda(UdaType::Date, &[])),
+        ]);
+        let cases = [
+            ("estimate", Value::from("1h30m"), Ok(())),
+            ("review", Value::from("2024-07-01"), Ok(())),
+            ("estimate", Value::from("soon"), Err("bad value \"soon\" for estimate")),
+            //dates are stored as YYYY-MM-DD, never as typed
+            ("review", Value::from("tomorrow"), Err("bad value \"tomorrow\" for review")),
+            ("review", Value::from("2024-13-01"), Err("bad value \"2024-13-01\" for review")),
+            //attributes that aren't declared are kept as they are
+            ("ticket", Value::from(12), Ok(())),
+        ];
+        for (name, value, expected) in cases {
+            let mut task = Task::new(1, "x".to_string(), String::new());
+            task.extra.insert(name.to_string(), value.clone());
+            assert_eq!(check_values(&udas, &task), expected.map_err(String::from), "{} = {}", name, value);
+        }
+    }
+
+    #[test]
+    fn checks_config_files() {
+        let cases = [
+            ("", Ok(())),
+            ("[uda.estimate]\ntype = \"duration\"\ndefault = \"1h\"", Ok(())),
+            ("[workflow]\nstates = [\"todo\", \"review\", \"done\"]", Ok(())),
+            ("[uda.due]\ntype = \"date\"", Err("\"due\" can't be used as an attribute name")),
+            ("[uda.\"a.b\"]\ntype = \"string\"", Err("\"a.b\" can't be used as an attribute name")),
+            ("[uda.customer]\ntype = \"enum\"", Err("enum attribute \"customer\" needs a list of values")),
+            ("[uda.size]\ntype = \"number\"\ndefault = \"big\"", Err("default for \"size\": \"big\" isn't a number")),
+            ("[workflow]\nstates = [\"todo\"]", Err("the workflow needs at least two states, one to start in and one for done")),
+            ("[workflow]\nstates = [\"todo\", \"Todo\"]", Err("workflow state \"Todo\" is listed twice")),
+            ("[workflow]\nstates = [\"to do\", \"done\"]", Err("\"to do\" can't be used as a workflow state")),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(parse(text).map(|_| ()), expected.map_err(String::from), "{:?}", text);
+        }
+        assert!(parse("[uda.x]\ntype = \"colour\"").is_err());
+    }
+}
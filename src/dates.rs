@@ -0,0 +1,486 @@
+//parsing of dates, times and durations as people type them, shared by every command that takes one.
+//
+//dates: 2024-06-07, june 7, 7 jun 2025, today, tomorrow, yesterday, friday or next friday (the next one
+//after today), next week (its monday), next month, next year, in 3 days, in 2 weeks, in 1 month,
+//end of week (eow), end of month (eom), end of year (eoy)
+//times: 17:00, 9:05, 5pm, 5:30 pm, noon, midnight
+//durations: 90m, 1h30m, 1.5h, 2d, 1w, 3 days, 2 hours
+//
+//words can be joined with - or _ so a date fits in one argument, as in due:next-friday or wait:in_3_days.
+
+use chrono::{DateTime, Datelike, Duration, FixedOffset, Local, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Timelike, Weekday};
+use chrono_tz::Tz;
+use std::fmt;
+
+//a date, with the time of day when one was given
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct When {
+    pub date: NaiveDate,
+    pub time: Option<NaiveTime>,
+}
+
+impl fmt::Display for When {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.date.format("%Y-%m-%d"))?;
+        if let Some(time) = self.time {
+            write!(f, " {}", time.format("%H:%M"))?;
+        }
+        Ok(())
+    }
+}
+
+pub const WEEKDAYS: [(&str, Weekday); 7] = [
+    ("monday", Weekday::Mon),
+    ("tuesday", Weekday::Tue),
+    ("wednesday", Weekday::Wed),
+    ("thursday", Weekday::Thu),
+    ("friday", Weekday::Fri),
+    ("saturday", Weekday::Sat),
+    ("sunday", Weekday::Sun),
+];
+
+const MONTHS: [&str; 12] = [
+    "january", "february", "march", "april", "may", "june",
+    "july", "august", "september", "october", "november", "december",
+];
+
+//words that tie a date to the text around it, as in "pay rent by friday"
+const CONNECTORS: [&str; 6] = ["on", "at", "by", "due", "before", "until"];
+
+fn invalid_date(input: &str) -> String {
+    format!("invalid date \"{}\". use YYYY-MM-DD, today, tomorrow, a day of the week, next friday, in 3 days or eom", input.trim())
+}
+
+//lowercase with single spaces. a - or _ next to a letter joins words, so 2024-06-07 keeps its dashes
+fn normalize(input: &str) -> String {
+    let chars: Vec<char> = input.trim().to_lowercase().chars().collect();
+    let mut text = String::new();
+    for (i, c) in chars.iter().enumerate() {
+        let by_letter = |j: Option<usize>| j.and_then(|j| chars.get(j)).is_some_and(|c| c.is_alphabetic());
+        if *c == '_' || (*c == '-' && (by_letter(i.checked_sub(1)) || by_letter(Some(i + 1)))) {
+            text.push(' ');
+        }
+        else {
+            text.push(*c);
+        }
+    }
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+pub fn parse_date(input: &str, today: NaiveDate) -> Result<NaiveDate, String> {
+    let text = normalize(input);
+    if let Ok(date) = NaiveDate::parse_from_str(&text, "%Y-%m-%d") {
+        return Ok(date);
+    }
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let date = match words.as_slice() {
+        ["today"] => Some(today),
+        ["tomorrow"] => today.succ_opt(),
+        ["yesterday"] => today.pred_opt(),
+        //the next one after today, so "monday" on a monday is a week away
+        [day] | ["next", day] if weekday(day).is_some() => weekday(day).map(|day| next_weekday(today, day)),
+        ["next", "week"] => Some(today + Duration::days(7 - today.weekday().num_days_from_monday() as i64)),
+        ["next", "month"] => today.with_day(1).and_then(|first| first.checked_add_months(Months::new(1))),
+        ["next", "year"] => NaiveDate::from_ymd_opt(today.year() + 1, 1, 1),
+        ["eow"] | ["end", "of", "week"] => Some(today + Duration::days(6 - today.weekday().num_days_from_monday() as i64)),
+        ["eom"] | ["end", "of", "month"] => last_of_month(today),
+        ["eoy"] | ["end", "of", "year"] => NaiveDate::from_ymd_opt(today.year(), 12, 31),
+        ["in", rest @ ..] => after(today, rest),
+        _ => month_day(&words, today),
+    };
+    date.ok_or_else(|| invalid_date(input))
+}
+
+//a weekday from its full lowercase name, as in an event's repeat
+pub fn weekday(word: &str) -> Option<Weekday> {
+    WEEKDAYS.iter().find(|(name, _)| *name == word).map(|(_, day)| *day)
+}
+
+fn next_weekday(today: NaiveDate, day: Weekday) -> NaiveDate {
+    let ahead = (day.num_days_from_monday() as i64 - today.weekday().num_days_from_monday() as i64 + 6) % 7 + 1;
+    today + Duration::days(ahead)
+}
+
+fn last_of_month(date: NaiveDate) -> Option<NaiveDate> {
+    date.with_day(1)?.checked_add_months(Months::new(1))?.pred_opt()
+}
+
+//"3 days", "2 weeks", "1 month" or a duration in whole days like 3d
+fn after(today: NaiveDate, words: &[&str]) -> Option<NaiveDate> {
+    if let [count, unit] = words {
+        let count: u32 = count.parse().ok()?;
+        match unit.trim_end_matches('s') {
+            "day" => return today.checked_add_signed(Duration::days(count as i64)),
+            "week" => return today.checked_add_signed(Duration::weeks(count as i64)),
+            "month" => return today.checked_add_months(Months::new(count)),
+            "year" => return today.checked_add_months(Months::new(count.checked_mul(12)?)),
+            _ => {},
+        }
+    }
+    let duration = parse_duration(&words.join(" ")).ok()?;
+    if duration <= Duration::zero() || duration.num_seconds() % 86400 != 0 {
+        return None;
+    }
+    today.checked_add_signed(duration)
+}
+
+//june 7, 7 june, jun 7th or june 7 2025. without a year it's the next june 7 from today on
+fn month_day(words: &[&str], today: NaiveDate) -> Option<NaiveDate> {
+    let month = |word: &str| MONTHS.iter().position(|m| *m == word || (word.len() >= 3 && m.starts_with(word))).map(|m| m as u32 + 1);
+    let day = |word: &str| {
+        let word = word.trim_end_matches(',');
+        let number = ["st", "nd", "rd", "th"].iter().find_map(|suffix| word.strip_suffix(suffix)).unwrap_or(word);
+        number.parse::<u32>().ok().filter(|d| (1..=31).contains(d) && number.chars().all(|c| c.is_ascii_digit()))
+    };
+    let (m, d, rest) = match words {
+        [a, b, rest @ ..] if month(a).is_some() && day(b).is_some() => (month(a)?, day(b)?, rest),
+        [a, b, rest @ ..] if day(a).is_some() && month(b).is_some() => (month(b)?, day(a)?, rest),
+        _ => return None,
+    };
+    match rest {
+        [year] if year.len() == 4 => NaiveDate::from_ymd_opt(year.parse().ok()?, m, d),
+        //february 29th is the next one there is
+        [] => (today.year()..today.year() + 8)
+            .filter_map(|year| NaiveDate::from_ymd_opt(year, m, d))
+            .find(|date| *date >= today),
+        _ => None,
+    }
+}
+
+//a time of day: 17:00, 9:05, 5pm, 5:30 pm, noon or midnight
+pub fn parse_time(input: &str) -> Result<NaiveTime, String> {
+    let mut time: String = input.to_lowercase().split_whitespace().collect();
+    let invalid = || format!("invalid time \"{}\". use 17:00, 5pm or 5:30pm", input.trim());
+    match time.as_str() {
+        "noon" => return Ok(NaiveTime::from_hms_opt(12, 0, 0).unwrap_or_default()),
+        "midnight" => return Ok(NaiveTime::MIN),
+        _ => {},
+    }
+    if time.ends_with("am") || time.ends_with("pm") {
+        //5pm is 5:00pm
+        if !time.contains(':') {
+            time.insert_str(time.len() - 2, ":00");
+        }
+        return NaiveTime::parse_from_str(&time, "%I:%M%p").map_err(|_| invalid());
+    }
+    //24 hour times need the minutes, a bare number is too likely to be something else
+    match time.split_once(':') {
+        Some((hours, minutes)) if !hours.is_empty() && hours.len() <= 2 && minutes.len() == 2 => {
+            NaiveTime::parse_from_str(&time, "%H:%M").map_err(|_| invalid())
+        },
+        _ => Err(invalid()),
+    }
+}
+
+//a time of day the way vayu shows it, ex: 9:30am or 12:05pm
+pub fn format_time(time: NaiveTime) -> String {
+    time.format("%-I:%M%P").to_string()
+}
+
+//a length of time: 90m, 1h30m, 1.5h, 2d, 1w, 3 days or 2 hours
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let text = normalize(input);
+    let invalid = || format!("invalid duration \"{}\". use something like 90m, 1h30m, 2h, 3d or 1w", input.trim());
+    let mut seconds = 0.0;
+    let mut rest = text.as_str();
+    if rest.is_empty() {
+        return Err(invalid());
+    }
+    while !rest.is_empty() {
+        let number_end = rest.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(rest.len());
+        let number: f64 = rest[..number_end].parse().map_err(|_| invalid())?;
+        rest = rest[number_end..].trim_start();
+        let unit_end = rest.find(|c: char| !c.is_alphabetic()).unwrap_or(rest.len());
+        let unit = match &rest[..unit_end] {
+            "w" | "week" | "weeks" => 604800.0,
+            "d" | "day" | "days" => 86400.0,
+            "h" | "hr" | "hrs" | "hour" | "hours" => 3600.0,
+            "m" | "min" | "mins" | "minute" | "minutes" => 60.0,
+            _ => return Err(invalid()),
+        };
+        seconds += number * unit;
+        rest = rest[unit_end..].trim_start();
+    }
+    //too long for a Duration is as invalid as a bad unit. the cast saturates, so try_seconds sees it
+    Duration::try_seconds(seconds.round() as i64).ok_or_else(invalid)
+}
+
+//a date with an optional time: friday, friday 17:00, tomorrow at 5pm, 5pm (today), in 2 hours,
+//2024-06-07T17:00. `now` is the local wall clock time
+pub fn parse_when(input: &str, now: NaiveDateTime) -> Result<When, String> {
+    let text = normalize(input);
+    let today = now.date();
+    if let Ok(date) = parse_date(&text, today) {
+        return Ok(When { date, time: None });
+    }
+    if let Ok(time) = parse_time(&text) {
+        return Ok(When { date: today, time: Some(time) });
+    }
+    //2024-06-07t17:00 in one word
+    if let Some((date, time)) = text.split_once('t').filter(|(date, _)| date.len() == 10) {
+        if let (Ok(date), Ok(time)) = (NaiveDate::parse_from_str(date, "%Y-%m-%d"), parse_time(time)) {
+            return Ok(When { date, time: Some(time) });
+        }
+    }
+    //in 2 hours, in 90m
+    if let Some(duration) = text.strip_prefix("in ").and_then(|rest| parse_duration(rest).ok()) {
+        if let Some(at) = now.checked_add_signed(duration).filter(|_| duration > Duration::zero()) {
+            let time = at.time().with_second(0).and_then(|time| time.with_nanosecond(0));
+            return Ok(When { date: at.date(), time });
+        }
+    }
+    //a date and a time either way round, as in friday 17:00, tomorrow at 5pm or 5pm on friday
+    let words: Vec<&str> = text.split_whitespace().collect();
+    for split in 1..words.len() {
+        let (first, second) = words.split_at(split);
+        let second = second.strip_prefix(&["at"]).or_else(|| second.strip_prefix(&["on"])).unwrap_or(second);
+        if let (Ok(date), Ok(time)) = (parse_date(&first.join(" "), today), parse_time(&second.join(" "))) {
+            return Ok(When { date, time: Some(time) });
+        }
+        if let (Ok(time), Ok(date)) = (parse_time(&first.join(" ")), parse_date(&second.join(" "), today)) {
+            return Ok(When { date, time: Some(time) });
+        }
+    }
+    Err(format!("{}, optionally followed by a time like 17:00 or 5pm", invalid_date(input)))
+}
+
+//finds a date in free text, as in "submit the report next friday at 5pm". the longest run of words
+//that reads as one is taken out, with a word like "on" or "by" in front of it, and the rest of the text
+//comes back with it
+pub fn find_when(text: &str, now: NaiveDateTime) -> Option<(When, String)> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    for len in (1..=words.len().min(6)).rev() {
+        for start in 0..=words.len() - len {
+            let end = start + len;
+            if let Ok(when) = parse_when(&words[start..end].join(" "), now) {
+                let start = match start.checked_sub(1) {
+                    Some(before) if CONNECTORS.contains(&words[before].to_lowercase().as_str()) => before,
+                    _ => start,
+                };
+                let rest: Vec<&str> = words[..start].iter().chain(&words[end..]).copied().collect();
+                return Some((when, rest.join(" ")));
+            }
+        }
+    }
+    None
+}
+
+//checks a time zone name like Europe/Berlin
+pub fn parse_zone(name: &str) -> Result<Tz, String> {
+    name.trim().parse::<Tz>().map_err(|_| format!("unknown time zone \"{}\". use a name like Europe/Berlin", name.trim()))
+}
+
+//the moment a date and time happen in a time zone, or in the local zone when none is given.
+//a time skipped by a daylight saving change is moved an hour later, and a repeated one takes the first.
+pub fn localize(date: NaiveDate, time: NaiveTime, zone: Option<&str>) -> Result<DateTime<FixedOffset>, String> {
+    let naive = date.and_time(time);
+    let resolve = |naive: NaiveDateTime| -> Option<DateTime<FixedOffset>> {
+        match zone {
+            Some(zone) => parse_zone(zone).ok()?.from_local_datetime(&naive).earliest().map(|dt| dt.fixed_offset()),
+            None => Local.from_local_datetime(&naive).earliest().map(|dt| dt.fixed_offset()),
+        }
+    };
+    if let Some(zone) = zone {
+        parse_zone(zone)?;
+    }
+    resolve(naive)
+        .or_else(|| resolve(naive + Duration::hours(1)))
+        .ok_or_else(|| format!("{} {} doesn't exist in that time zone", date, time.format("%H:%M")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //a wednesday
+    fn now() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 6, 12).unwrap().and_hms_opt(10, 15, 30).unwrap()
+    }
+
+    fn date(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn dates() {
+        let cases = [
+            ("2024-07-01", Some("2024-07-01")),
+            ("  2024-07-01 ", Some("2024-07-01")),
+            ("today", Some("2024-06-12")),
+            ("Today", Some("2024-06-12")),
+            ("tomorrow", Some("2024-06-13")),
+            ("yesterday", Some("2024-06-11")),
+            ("friday", Some("2024-06-14")),
+            ("next friday", Some("2024-06-14")),
+            ("next-friday", Some("2024-06-14")),
+            ("wednesday", Some("2024-06-19")),
+            ("monday", Some("2024-06-17")),
+            ("next week", Some("2024-06-17")),
+            ("next month", Some("2024-07-01")),
+            ("next year", Some("2025-01-01")),
+            ("in 3 days", Some("2024-06-15")),
+            ("in 1 day", Some("2024-06-13")),
+            ("in_2_weeks", Some("2024-06-26")),
+            ("in 1 month", Some("2024-07-12")),
+            ("in 2 years", Some("2026-06-12")),
+            ("in 3d", Some("2024-06-15")),
+            ("in 1w", Some("2024-06-19")),
+            ("eow", Some("2024-06-16")),
+            ("end of week", Some("2024-06-16")),
+            ("eom", Some("2024-06-30")),
+            ("end of month", Some("2024-06-30")),
+            ("end-of-month", Some("2024-06-30")),
+            ("eoy", Some("2024-12-31")),
+            ("june 20", Some("2024-06-20")),
+            ("jun 20th", Some("2024-06-20")),
+            ("20 june", Some("2024-06-20")),
+            ("june 1", Some("2025-06-01")),
+            ("feb 29", Some("2028-02-29")),
+            ("march 3 2026", Some("2026-03-03")),
+            ("", None),
+            ("2024-02-30", None),
+            ("2024-13-01", None),
+            ("fri", None),
+            ("someday", None),
+            ("next", None),
+            ("next tuesdays", None),
+            ("in 3", None),
+            ("in 3 fortnights", None),
+            ("in 2 hours", None),
+            ("june 31", None),
+            ("june", None),
+            ("june 5 25", None),
+            ("june 5pm", None),
+        ];
+        for (input, expected) in cases {
+            let parsed = parse_date(input, now().date()).ok();
+            assert_eq!(parsed, expected.map(date), "parse_date({:?})", input);
+        }
+    }
+
+    #[test]
+    fn end_of_month_in_short_months() {
+        assert_eq!(parse_date("eom", date("2024-02-10")), Ok(date("2024-02-29")));
+        assert_eq!(parse_date("eom", date("2023-02-10")), Ok(date("2023-02-28")));
+        assert_eq!(parse_date("in 1 month", date("2024-01-31")), Ok(date("2024-02-29")));
+    }
+
+    #[test]
+    fn times() {
+        let cases = [
+            ("17:00", Some((17, 0))),
+            ("9:05", Some((9, 5))),
+            ("5:30", Some((5, 30))),
+            ("09:05", Some((9, 5))),
+            ("0:00", Some((0, 0))),
+            ("23:59", Some((23, 59))),
+            ("5pm", Some((17, 0))),
+            ("5 pm", Some((17, 0))),
+            ("5PM", Some((17, 0))),
+            ("5:30pm", Some((17, 30))),
+            ("5:30 am", Some((5, 30))),
+            ("12am", Some((0, 0))),
+            ("12pm", Some((12, 0))),
+            ("12:30pm", Some((12, 30))),
+            ("noon", Some((12, 0))),
+            ("midnight", Some((0, 0))),
+            ("", None),
+            ("5", None),
+            ("1700", None),
+            ("24:00", None),
+            ("17:60", None),
+            ("17:5", None),
+            ("13pm", None),
+            ("0am", None),
+            ("5:30xm", None),
+            ("pm", None),
+        ];
+        for (input, expected) in cases {
+            let parsed = parse_time(input).ok();
+            let expected = expected.and_then(|(h, m)| NaiveTime::from_hms_opt(h, m, 0));
+            assert_eq!(parsed, expected, "parse_time({:?})", input);
+        }
+    }
+
+    #[test]
+    fn durations() {
+        let cases = [
+            ("90m", Some(90)),
+            ("1h30m", Some(90)),
+            ("1h 30m", Some(90)),
+            ("1.5h", Some(90)),
+            ("2h", Some(120)),
+            ("2 hours", Some(120)),
+            ("45 minutes", Some(45)),
+            ("1d", Some(1440)),
+            ("3 days", Some(4320)),
+            ("1w", Some(10080)),
+            ("0m", Some(0)),
+            ("", None),
+            ("90", None),
+            ("h", None),
+            ("2x", None),
+            ("1h30", None),
+            ("ten minutes", None),
+            //more than a Duration can hold
+            ("99999999999w", None),
+            ("99999999999999999999999h", None),
+        ];
+        for (input, expected) in cases {
+            let parsed = parse_duration(input).ok().map(|d| d.num_minutes());
+            assert_eq!(parsed, expected, "parse_duration({:?})", input);
+        }
+    }
+
+    #[test]
+    fn dates_with_times() {
+        let cases = [
+            ("friday", Some(("2024-06-14", None))),
+            ("friday 17:00", Some(("2024-06-14", Some((17, 0))))),
+            ("friday 5pm", Some(("2024-06-14", Some((17, 0))))),
+            ("friday 5 pm", Some(("2024-06-14", Some((17, 0))))),
+            ("tomorrow at 9am", Some(("2024-06-13", Some((9, 0))))),
+            ("next friday at noon", Some(("2024-06-14", Some((12, 0))))),
+            ("5pm on friday", Some(("2024-06-14", Some((17, 0))))),
+            ("17:00", Some(("2024-06-12", Some((17, 0))))),
+            ("2024-07-01 08:30", Some(("2024-07-01", Some((8, 30))))),
+            ("2024-07-01T08:30", Some(("2024-07-01", Some((8, 30))))),
+            ("in 2 hours", Some(("2024-06-12", Some((12, 15))))),
+            ("in 90m", Some(("2024-06-12", Some((11, 45))))),
+            ("in 14h", Some(("2024-06-13", Some((0, 15))))),
+            ("in 3 days", Some(("2024-06-15", None))),
+            ("eom 18:00", Some(("2024-06-30", Some((18, 0))))),
+            ("friday 25:00", None),
+            ("friday at", None),
+            ("tomorrow yoga", None),
+            ("in 0m", None),
+            ("in_99999999999w", None),
+            ("in 9999999999h", None),
+        ];
+        for (input, expected) in cases {
+            let parsed = parse_when(input, now()).ok();
+            let expected = expected.map(|(d, t)| When { date: date(d), time: t.and_then(|(h, m)| NaiveTime::from_hms_opt(h, m, 0)) });
+            assert_eq!(parsed, expected, "parse_when({:?})", input);
+        }
+    }
+
+    #[test]
+    fn dates_in_text() {
+        let cases = [
+            ("test at end of month", Some(("2024-06-30", "test"))),
+            ("submit report next friday at 5pm", Some(("2024-06-14 17:00", "submit report"))),
+            ("call mom tomorrow", Some(("2024-06-13", "call mom"))),
+            ("pay rent by friday please", Some(("2024-06-14", "pay rent please"))),
+            ("standup at 9:30am", Some(("2024-06-12 09:30", "standup"))),
+            ("dentist on june 20 at 3pm", Some(("2024-06-20 15:00", "dentist"))),
+            ("renew passport in 2 weeks", Some(("2024-06-26", "renew passport"))),
+            ("read a book", None),
+            ("", None),
+        ];
+        for (input, expected) in cases {
+            let found = find_when(input, now()).map(|(when, rest)| (when.to_string(), rest));
+            let expected = expected.map(|(when, rest)| (when.to_string(), rest.to_string()));
+            assert_eq!(found, expected, "find_when({:?})", input);
+        }
+    }
+}
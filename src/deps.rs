@@ -0,0 +1,134 @@
+//dependencies between tasks. a task lists the ids of the tasks it waits on in `depends`,
+//and is blocked while any of them is still pending. done, archived or missing tasks don't block.
+
+use crate::store::Task;
+use std::collections::HashSet;
+
+pub fn is_blocked(tasks: &[Task], task: &Task) -> bool {
+    !task.done && task.depends.iter().any(|id| tasks.iter().any(|t| t.id == *id && !t.done))
+}
+
+//true if some pending task is waiting on this one
+pub fn is_blocking(tasks: &[Task], task: &Task) -> bool {
+    !task.done && tasks.iter().any(|t| !t.done && t.depends.contains(&task.id))
+}
+
+//checks the dependencies `task` would have if it were saved into `tasks`:
+//every one must exist and following them must never lead back to the task
+pub fn check(tasks: &[Task], task: &Task) -> Result<(), String> {
+    for id in &task.depends {
+        if *id == task.id {
+            return Err(format!("task {} can't depend on itself", task.id));
+        }
+        if !tasks.iter().any(|t| t.id == *id) {
+            return Err(format!("task with id {} not found", id));
+        }
+    }
+    //walk everything the task waits on, using the new dependencies for the task itself
+    let mut seen = HashSet::new();
+    let mut queue: Vec<i32> = task.depends.clone();
+    while let Some(id) = queue.pop() {
+        if id == task.id {
+            return Err(format!("that would make a dependency cycle through task {}", task.id));
+        }
+        if !seen.insert(id) {
+            continue;
+        }
+        if let Some(next) = tasks.iter().find(|t| t.id == id) {
+            queue.extend(&next.depends);
+        }
+    }
+    Ok(())
+}
+
+//the dependency graph in graphviz DOT format. an edge goes from a task to the task waiting on it,
+//so the graph reads in the order the work has to happen. ex: vayu graph | dot -Tpng > deps.png
+pub fn graph(tasks: &[Task]) -> String {
+    let mut ids: HashSet<i32> = HashSet::new();
+    for task in tasks.iter().filter(|t| !t.done && !t.depends.is_empty()) {
+        ids.insert(task.id);
+        ids.extend(&task.depends);
+    }
+    let mut dot = String::from("digraph vayu {\n    rankdir=LR;\n    node [shape=box];\n");
+    for task in tasks.iter().filter(|t| ids.contains(&t.id)) {
+        let style = if task.done {
+            ", style=dashed, color=gray"
+        }
+        else if is_blocked(tasks, task) {
+            ", color=gray"
+        }
+        else {
+            ""
+        };
+        dot.push_str(&format!("    t{} [label=\"{}: {}\"{}];\n", task.id, task.id, escape(&task.description), style));
+    }
+    for task in tasks.iter().filter(|t| ids.contains(&t.id)) {
+        for id in task.depends.iter().filter(|id| ids.contains(id) && tasks.iter().any(|t| t.id == **id)) {
+            dot.push_str(&format!("    t{} -> t{};\n", id, task.id));
+        }
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(id: i32, depends: &[i32], done: bool) -> Task {
+        let mut task = Task::new(id, format!("task {}", id), String::new());
+        task.depends = depends.to_vec();
+        task.done = done;
+        task
+    }
+
+    //1 waits on 2, 2 waits on 3. 4 is done and 5 waits on it
+    fn tasks() -> Vec<Task> {
+        vec![task(1, &[2], false), task(2, &[3], false), task(3, &[], false), task(4, &[], true), task(5, &[4], false)]
+    }
+
+    #[test]
+    fn checks_dependencies() {
+        let tasks = tasks();
+        let cases = [
+            (task(3, &[], false), Ok(())),
+            (task(6, &[1, 5], false), Ok(())),
+            //depending on a task twice, by two routes, isn't a cycle
+            (task(6, &[1, 2, 3], false), Ok(())),
+            (task(3, &[4], false), Ok(())),
+            (task(3, &[3], false), Err("task 3 can't depend on itself")),
+            (task(3, &[7], false), Err("task with id 7 not found")),
+            (task(3, &[2], false), Err("that would make a dependency cycle through task 3")),
+            (task(3, &[1], false), Err("that would make a dependency cycle through task 3")),
+            (task(2, &[1], false), Err("that would make a dependency cycle through task 2")),
+        ];
+        for (task, expected) in cases {
+            assert_eq!(check(&tasks, &task), expected.map_err(String::from), "task {} depending on {:?}", task.id, task.depends);
+        }
+    }
+
+    #[test]
+    fn blocked_and_blocking() {
+        let tasks = tasks();
+        let cases = [(1, true, false), (2, true, true), (3, false, true), (4, false, false), (5, false, false)];
+        for (id, blocked, blocking) in cases {
+            let task = tasks.iter().find(|t| t.id == id).unwrap();
+            assert_eq!((is_blocked(&tasks, task), is_blocking(&tasks, task)), (blocked, blocking), "task {}", id);
+        }
+    }
+
+    #[test]
+    fn graphs_pending_dependencies() {
+        let dot = graph(&tasks());
+        for edge in ["t2 -> t1", "t3 -> t2", "t4 -> t5"] {
+            assert!(dot.contains(edge), "missing {}", edge);
+        }
+        assert!(dot.contains("t4 [label=\"4: task 4\", style=dashed, color=gray]"));
+        assert!(dot.contains("t1 [label=\"1: task 1\", color=gray]"));
+        assert!(dot.contains("t3 [label=\"3: task 3\"]"));
+    }
+}
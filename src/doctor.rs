@@ -0,0 +1,134 @@
+//`vayu doctor`: checks the task and event stores line by line and reports anything that
+//vayu can't use. with --fix the bad lines are moved into a `.rejected` file next to the store.
+
+use crate::store::{self, Event1, Header, Task};
+use serde::de::DeserializeOwned;
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+struct Problem {
+    line: usize,
+    message: String,
+}
+
+//returns the number of problems found
+pub fn run(dir: &Path, fix: bool) -> io::Result<usize> {
+    fs::create_dir_all(dir)?;
+    //hold the lock so nobody writes the stores while they are being repaired
+    let _lock = store::acquire_lock(dir)?;
+    let mut total = 0;
+    total += check_file::<Task>(dir, store::TASKS_FILE, "tasks", |t| t.id, store::check_task, fix)?;
+    total += check_file::<Event1>(dir, store::EVENTS_FILE, "events", |e| e.id, store::check_event, fix)?;
+    if total == 0 {
+        println!("no problems found");
+    }
+    else if !fix {
+        println!("found {} problems. run `vayu doctor --fix` to move the bad records aside", total);
+    }
+    Ok(total)
+}
+
+fn check_file<T: DeserializeOwned>(
+    dir: &Path,
+    name: &str,
+    kind: &str,
+    id: impl Fn(&T) -> i32,
+    check: impl Fn(&T) -> Result<(), String>,
+    fix: bool,
+) -> io::Result<usize> {
+    let path = dir.join(name);
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e),
+    };
+    let lines: Vec<&str> = contents.lines().collect();
+    let mut problems = Vec::new();
+    let mut seen = HashSet::new();
+    let mut header_ok = false;
+    //the first non blank line is the header
+    let header_line = lines.iter().position(|line| !line.trim().is_empty());
+    for (i, line) in lines.iter().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if Some(i) == header_line {
+            match serde_json::from_str::<Header>(line) {
+                Ok(header) if header.kind == kind => header_ok = true,
+                Ok(header) => problems.push(Problem { line: i + 1, message: format!("header is for a {} store, expected {}", header.kind, kind) }),
+                Err(e) => problems.push(Problem { line: i + 1, message: format!("bad header: {}", e) }),
+            }
+            continue;
+        }
+        if let Err(e) = store::parse_record(line, &id, &check, &mut seen) {
+            problems.push(Problem { line: i + 1, message: e });
+        }
+    }
+    for problem in &problems {
+        println!("{}:{}: {}", path.display(), problem.line, problem.message);
+    }
+    if fix && !problems.is_empty() {
+        quarantine(&path, kind, &lines, &problems, header_ok)?;
+    }
+    Ok(problems.len())
+}
+
+//appends the bad lines to `<store>.rejected` and rewrites the store without them
+fn quarantine(path: &Path, kind: &str, lines: &[&str], problems: &[Problem], header_ok: bool) -> io::Result<()> {
+    let bad: HashSet<usize> = problems.iter().map(|p| p.line).collect();
+    let mut rejected = Vec::new();
+    let mut kept = Vec::new();
+    if !header_ok {
+        //the old header is unreadable, start over with a fresh one
+        kept.extend(serde_json::to_vec(&Header::new(kind))?);
+        kept.push(b'\n');
+    }
+    for (i, line) in lines.iter().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let out = if bad.contains(&(i + 1)) { &mut rejected } else { &mut kept };
+        out.extend_from_slice(line.as_bytes());
+        out.push(b'\n');
+    }
+    let mut rejected_name = path.as_os_str().to_os_string();
+    rejected_name.push(".rejected");
+    let rejected_path = Path::new(&rejected_name);
+    let mut file = fs::OpenOptions::new().create(true).append(true).open(rejected_path)?;
+    file.write_all(&rejected)?;
+    file.sync_all()?;
+    store::write_atomic(path, &kept)?;
+    println!("moved {} records from {} to {}", problems.len(), path.display(), rejected_path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_what_loading_keeps() {
+        let dir = store::temp_dir("doctor");
+        let lines = [
+            r#"{"kind":"tasks","version":1,"next_id":3}"#,
+            r#"{"id":1,"uuid":"00000000-0000-4000-8000-000000000001","description":"bad due","due":"friday","done":false}"#,
+            r#"{"id":1,"uuid":"00000000-0000-4000-8000-000000000002","description":"good","due":"","done":false}"#,
+            r#"{"id":2,"uuid":"00000000-0000-4000-8000-000000000003","description":"second","due":"","done":false}"#,
+            r#"{"id":2,"uuid":"00000000-0000-4000-8000-000000000004","description":"copy","due":"","done":false}"#,
+            r#"not json"#,
+        ];
+        fs::write(dir.join(store::TASKS_FILE), lines.join("\n")).unwrap();
+        let loaded: Vec<String> = store::Store::load_quietly(&dir, store::Access::Read).unwrap()
+            .tasks.iter().map(|t| t.description.clone()).collect();
+        assert_eq!(loaded, ["good", "second"]);
+        assert_eq!(run(&dir, true).unwrap(), 3);
+        let kept = fs::read_to_string(dir.join(store::TASKS_FILE)).unwrap();
+        assert_eq!(kept, [lines[0], lines[2], lines[3]].join("\n") + "\n");
+        let rejected = fs::read_to_string(dir.join(format!("{}.rejected", store::TASKS_FILE))).unwrap();
+        assert_eq!(rejected, [lines[1], lines[4], lines[5]].join("\n") + "\n");
+        assert_eq!(run(&dir, false).unwrap(), 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
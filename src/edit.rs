@@ -0,0 +1,451 @@
+//changing existing tasks, either inline with `vayu modify` or in $EDITOR with `vayu edit`
+
+use crate::clock;
+use crate::config;
+use crate::dates;
+use crate::deps;
+use crate::journal;
+use crate::recur;
+use crate::store::{self, Access, Store, Task};
+use chrono::{NaiveDate, NaiveTime};
+use std::env;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+//applies `vayu modify` arguments to a task. `key:value` words set that field,
+//everything else is joined up and becomes the new description.
+//ex: vayu modify 4 due:friday priority:H "submit the report"
+pub fn modify(task: &mut Task, args: &[String]) -> Result<(), String> {
+    let (fields, description) = split_fields(args, true);
+    for (key, value) in fields {
+        set_field(task, &key, &value)?;
+    }
+    if !description.is_empty() {
+        set_field(task, "description", &description)?;
+    }
+    Ok(())
+}
+
+//separates `key:value` words for known fields from the rest of the text.
+//+tag and @context words come back as fields too, keyed "+" and "@". when `removals` is set
+//-tag / -@context come back as "-" and "-@" so modify can take them off again, otherwise they
+//are left in the text (a new task has nothing to remove, and "-la" is more likely a flag).
+pub fn split_fields(args: &[String], removals: bool) -> (Vec<(String, String)>, String) {
+    let now = clock::now().naive_local();
+    let words: Vec<&str> = args.iter().flat_map(|arg| split_words(arg)).collect();
+    let mut fields = Vec::new();
+    let mut description = Vec::new();
+    let mut i = 0;
+    while i < words.len() {
+        let word = words[i];
+        i += 1;
+        if let Some((prefix, name)) = label(word).filter(|(prefix, _)| removals || !prefix.starts_with('-')) {
+            fields.push((prefix.to_string(), name.to_string()));
+            continue;
+        }
+        match word.split_once(':') {
+            Some((key, value)) if is_field(key) => {
+                let mut value = value.to_string();
+                //a date can run over several words, as in due:next friday 17:00. the longest run that reads as one is taken
+                if !value.is_empty() && matches!(key, "due" | "wait" | "scheduled" | "sched") {
+                    let reads = |text: &str| match key {
+                        "due" => dates::parse_when(text, now).is_ok(),
+                        _ => dates::parse_date(text, now.date()).is_ok(),
+                    };
+                    let longest = (1..=(words.len() - i).min(4)).rev()
+                        .find(|&n| reads(&format!("{} {}", value, words[i..i + n].join(" "))));
+                    if let Some(n) = longest {
+                        value = format!("{} {}", value, words[i..i + n].join(" "));
+                        i += n;
+                    }
+                }
+                fields.push((key.to_string(), value));
+            },
+            _ => description.push(word),
+        }
+    }
+    (fields, description.join(" "))
+}
+
+//a quoted argument is kept whole unless it holds a tag or context, which are picked out of the text
+fn split_words(arg: &str) -> Vec<&str> {
+    if arg.split_whitespace().any(|word| label(word).is_some()) {
+        arg.split_whitespace().collect()
+    }
+    else {
+        vec![arg]
+    }
+}
+
+//+tag, @context, -tag or -@context
+pub fn label(word: &str) -> Option<(&str, &str)> {
+    ["-@", "+", "@", "-"].iter()
+        .find_map(|prefix| word.strip_prefix(prefix).map(|name| (*prefix, name)))
+        .filter(|(_, name)| store::valid_label(name))
+}
+
+fn is_field(key: &str) -> bool {
+    matches!(key, "due" | "tz" | "wait" | "scheduled" | "sched" | "priority" | "pri" | "depends" | "dep" | "recur")
+        || config::uda(key).is_some()
+}
+
+//H, M and L or 1 to 4, with 1 the most important. an empty value clears the priority
+pub fn parse_priority(value: &str) -> Result<Option<u8>, String> {
+    match value.trim().to_uppercase().as_str() {
+        "" => Ok(None),
+        "H" | "1" => Ok(Some(1)),
+        "M" | "2" => Ok(Some(2)),
+        "L" | "3" => Ok(Some(3)),
+        "4" => Ok(Some(4)),
+        _ => Err(format!("invalid priority \"{}\". use H, M, L or 1 to 4", value.trim())),
+    }
+}
+
+//sets one field from its text form, checking the value first
+pub fn set_field(task: &mut Task, key: &str, value: &str) -> Result<(), String> {
+    let value = value.trim();
+    match key {
+        "description" => {
+            if value.is_empty() {
+                return Err("description can't be empty".to_string());
+            }
+            task.description = value.to_string();
+        },
+        "due" => {
+            task.due_at = None;
+            if value.is_empty() {
+                task.due = String::new();
+            }
+            else {
+                let when = dates::parse_when(value, clock::now().naive_local())?;
+                match when.time {
+                    Some(time) => set_due_at(task, when.date, time)?,
+                    None => task.due = when.date.format("%Y-%m-%d").to_string(),
+                }
+            }
+        },
+        //the zone a due time is in. a time that's already set keeps its clock time in the new zone
+        "tz" => {
+            if !value.is_empty() {
+                dates::parse_zone(value)?;
+            }
+            let wall = task.due_at.map(|due_at| due_at.naive_local());
+            task.due_tz = if value.is_empty() { None } else { Some(value.to_string()) };
+            if let Some(wall) = wall {
+                set_due_at(task, wall.date(), wall.time())?;
+            }
+        },
+        "wait" => task.wait = optional_date(value)?,
+        "scheduled" | "sched" => task.scheduled = optional_date(value)?,
+        "priority" | "pri" => {
+            task.priority = parse_priority(value)?;
+        },
+        //whole lists, as written in the edit form
+        "tags" | "contexts" => {
+            let mut names = Vec::new();
+            for name in value.split_whitespace() {
+                let name = name.trim_start_matches(['+', '@']);
+                if !store::valid_label(name) {
+                    return Err(format!("bad {} name \"{}\"", &key[..key.len() - 1], name));
+                }
+                if !names.iter().any(|n| n == name) {
+                    names.push(name.to_string());
+                }
+            }
+            if key == "tags" { task.tags = names } else { task.contexts = names }
+        },
+        //depends:3,5 adds dependencies and depends:-3 takes one off. depends: on its own clears them
+        "depends" | "dep" => {
+            if value.is_empty() {
+                task.depends.clear();
+            }
+            for id in value.split(',').filter(|id| !id.trim().is_empty()) {
+                let (remove, id) = match id.trim().strip_prefix('-') {
+                    Some(id) => (true, id),
+                    None => (false, id.trim()),
+                };
+                let id: i32 = id.parse().map_err(|_| format!("invalid task id \"{}\" in depends", id))?;
+                if remove {
+                    task.depends.retain(|d| *d != id);
+                }
+                else if !task.depends.contains(&id) {
+                    task.depends.push(id);
+                }
+            }
+        },
+        //stored in its canonical form so the same rule always reads the same
+        "recur" => {
+            task.recur = if value.is_empty() { None } else { Some(recur::parse(value)?.to_string()) };
+        },
+        //the whole list, as written in the edit form
+        "dependencies" => {
+            task.depends.clear();
+            for id in value.split_whitespace() {
+                let id: i32 = id.parse().map_err(|_| format!("invalid task id \"{}\" in dependencies", id))?;
+                if !task.depends.contains(&id) {
+                    task.depends.push(id);
+                }
+            }
+        },
+        "+" => add_label(&mut task.tags, value),
+        "@" => add_label(&mut task.contexts, value),
+        "-" => task.tags.retain(|t| t != value),
+        "-@" => task.contexts.retain(|c| c != value),
+        //attributes declared in config.toml. an empty value removes it
+        _ => match config::uda(key) {
+            Some(_) if value.is_empty() => {
+                task.extra.remove(key);
+            },
+            Some(uda) => {
+                let value = config::parse_value(uda, value).map_err(|e| format!("{}: {}", key, e))?;
+                task.extra.insert(key.to_string(), value);
+            },
+            None => return Err(format!("unknown field \"{}\"", key)),
+        },
+    }
+    Ok(())
+}
+
+//a date field that can be cleared with an empty value
+fn optional_date(value: &str) -> Result<Option<String>, String> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    let date = dates::parse_date(value, clock::now().date_naive())?;
+    Ok(Some(date.format("%Y-%m-%d").to_string()))
+}
+
+//sets the due time from a date and a time in the task's zone
+fn set_due_at(task: &mut Task, date: NaiveDate, time: NaiveTime) -> Result<(), String> {
+    let due_at = dates::localize(date, time, task.due_tz.as_deref())?;
+    task.due = due_at.format("%Y-%m-%d").to_string();
+    task.due_at = Some(due_at);
+    Ok(())
+}
+
+fn add_label(labels: &mut Vec<String>, name: &str) {
+    if !labels.iter().any(|l| l == name) {
+        labels.push(name.to_string());
+    }
+}
+
+//the task as a text form for the editor
+fn to_form(task: &Task) -> String {
+    let mut form = String::new();
+    form.push_str(&format!("# editing task {} ({})\n", task.id, task.uuid));
+    form.push_str("# change the values after the colons, then save and close the editor.\n");
+    form.push_str("# lines starting with # are ignored. leave the file unchanged to cancel.\n");
+    form.push_str(&format!("description: {}\n", task.description));
+    //a due time is shown in its own zone, so it reads the same as when it was typed
+    let due = match task.due_at {
+        Some(due_at) => due_at.naive_local().format("%Y-%m-%d %H:%M").to_string(),
+        None => task.due.clone(),
+    };
+    form.push_str(&format!("due: {}\n", due));
+    form.push_str(&format!("tz: {}\n", task.due_tz.clone().unwrap_or_default()));
+    form.push_str(&format!("wait: {}\n", task.wait.clone().unwrap_or_default()));
+    form.push_str(&format!("scheduled: {}\n", task.scheduled.clone().unwrap_or_default()));
+    for (name, _) in config::udas() {
+        form.push_str(&format!("{}: {}\n", name, config::show(task.extra.get(name))));
+    }
+    form.push_str(&format!("priority: {}\n", task.priority.map(|p| p.to_string()).unwrap_or_default()));
+    form.push_str(&format!("tags: {}\n", task.tags.join(" ")));
+    form.push_str(&format!("contexts: {}\n", task.contexts.join(" ")));
+    let depends: Vec<String> = task.depends.iter().map(|id| id.to_string()).collect();
+    form.push_str(&format!("dependencies: {}\n", depends.join(" ")));
+    form.push_str(&format!("recur: {}\n", task.recur.clone().unwrap_or_default()));
+    form
+}
+
+//reads the form back into a copy of the task
+fn from_form(task: &Task, form: &str) -> Result<Task, String> {
+    let mut edited = task.clone();
+    for (i, line) in form.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (key, value) = line.split_once(':')
+            .ok_or_else(|| format!("line {}: expected \"field: value\"", i + 1))?;
+        set_field(&mut edited, key.trim(), value).map_err(|e| format!("line {}: {}", i + 1, e))?;
+    }
+    Ok(edited)
+}
+
+fn editor() -> String {
+    env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .ok()
+        .filter(|e| !e.trim().is_empty())
+        .unwrap_or_else(|| if cfg!(windows) { "notepad".to_string() } else { "vi".to_string() })
+}
+
+//opens the task in $EDITOR. the store isn't locked while the editor is open, so when it closes
+//the store is reloaded and the edit is only applied if nobody changed the task in the meantime.
+pub fn edit(dir: &Path, tasks: &[Task], key: &str) -> io::Result<()> {
+    let original = match store::find_task(tasks, key) {
+        Ok(index) => tasks[index].clone(),
+        Err(e) => {
+            println!("{}", e);
+            return Ok(());
+        }
+    };
+    let path = env::temp_dir().join(format!("vayu-task-{}.txt", store::short_uuid(&original.uuid)));
+    let mut form = to_form(&original);
+    let edited = loop {
+        fs::write(&path, &form)?;
+        let editor = editor();
+        let mut parts = editor.split_whitespace();
+        let program = parts.next().unwrap_or("vi");
+        let status = Command::new(program).args(parts).arg(&path).status();
+        match status {
+            Ok(status) if status.success() => {},
+            Ok(_) | Err(_) => {
+                fs::remove_file(&path).ok();
+                println!("editor \"{}\" failed, task not changed", editor);
+                return Ok(());
+            }
+        }
+        let saved = fs::read_to_string(&path)?;
+        if saved == form {
+            fs::remove_file(&path).ok();
+            println!("no changes made");
+            return Ok(());
+        }
+        match from_form(&original, &saved) {
+            Ok(edited) => break edited,
+            Err(e) => {
+                println!("{}", e);
+                println!("edit again? (y/n)");
+                let mut confirm = String::new();
+                io::stdin().read_line(&mut confirm)?;
+                if confirm.trim() != "y" {
+                    fs::remove_file(&path).ok();
+                    println!("task not changed");
+                    return Ok(());
+                }
+                //keep what they typed so the mistake can be fixed in place
+                form = saved;
+            }
+        }
+    };
+    fs::remove_file(&path).ok();
+
+    let mut store = Store::load(dir, Access::Write)?;
+    let before = journal::snapshot(&store);
+    if let Err(e) = deps::check(&store.tasks, &edited) {
+        println!("{}. task not changed", e);
+        return Ok(());
+    }
+    let current = store.tasks.iter_mut().find(|t| t.uuid == original.uuid);
+    match current {
+        Some(current) if serde_json::to_value(&*current)? == serde_json::to_value(&original)? => {
+            *current = edited;
+        },
+        Some(_) => {
+            println!("task {} was changed by another command while it was being edited, not saving", original.id);
+            return Ok(());
+        },
+        None => {
+            println!("task {} was removed while it was being edited, not saving", original.id);
+            return Ok(());
+        }
+    }
+    journal::save_and_record(&mut store, &before, "edit")?;
+    println!("task {} updated", original.id);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(words: &[&str]) -> Vec<String> {
+        words.iter().map(|w| w.to_string()).collect()
+    }
+
+    fn task() -> Task {
+        let mut task = Task::new(4, "submit the report".to_string(), String::new());
+        task.depends = vec![3];
+        task
+    }
+
+    #[test]
+    fn splits_fields_from_text() {
+        clock::set_for_test(clock::parse("2024-06-12 09:00").unwrap());
+        //fields written key=value, comma separated
+        let cases = [
+            (vec!["due:friday", "pay rent"], false, "due=friday", "pay rent"),
+            (vec!["due:next", "friday", "17:00", "ship"], false, "due=next friday 17:00", "ship"),
+            (vec!["wait:in", "3", "days", "call"], false, "wait=in 3 days", "call"),
+            (vec!["fix +infra @home -la"], false, "+=infra, @=home", "fix -la"),
+            (vec!["fix +infra -old -@home"], true, "+=infra, -=old, -@=home", "fix"),
+            (vec!["pri:H", "dep:3,5", "x"], true, "pri=H, dep=3,5", "x"),
+            //a colon in a quoted description isn't a field
+            (vec!["note: call bob"], false, "", "note: call bob"),
+        ];
+        for (words, removals, fields, description) in cases {
+            let (split, text) = split_fields(&args(&words), removals);
+            let split: Vec<String> = split.iter().map(|(key, value)| format!("{}={}", key, value)).collect();
+            assert_eq!((split.join(", ").as_str(), text.as_str()), (fields, description), "{:?}", words);
+        }
+    }
+
+    #[test]
+    fn sets_fields() {
+        clock::set_for_test(clock::parse("2024-06-12 09:00").unwrap());
+        //the form line each change shows up on, or the error it gives
+        let cases = [
+            ("due", "2024-07-01", Ok("due: 2024-07-01")),
+            ("due", "tomorrow 17:00", Ok("due: 2024-06-13 17:00")),
+            ("due", "", Ok("due: ")),
+            ("wait", "someday", Err("invalid date \"someday\". use YYYY-MM-DD, today, tomorrow, a day of the week, next friday, in 3 days or eom")),
+            ("wait", "friday", Ok("wait: 2024-06-14")),
+            ("wait", "", Ok("wait: ")),
+            ("sched", "in 2 weeks", Ok("scheduled: 2024-06-26")),
+            ("pri", "h", Ok("priority: 1")),
+            ("priority", "4", Ok("priority: 4")),
+            ("priority", "5", Err("invalid priority \"5\". use H, M, L or 1 to 4")),
+            ("tags", "+infra ops infra", Ok("tags: infra ops")),
+            ("tags", "ops 2fa", Err("bad tag name \"2fa\"")),
+            ("+", "infra", Ok("tags: infra")),
+            ("@", "home", Ok("contexts: home")),
+            ("depends", "5,3", Ok("dependencies: 3 5")),
+            ("depends", "-3", Ok("dependencies: ")),
+            ("depends", "", Ok("dependencies: ")),
+            ("depends", "x", Err("invalid task id \"x\" in depends")),
+            ("dependencies", "7 8 7", Ok("dependencies: 7 8")),
+            ("description", "  send it  ", Ok("description: send it")),
+            ("description", " ", Err("description can't be empty")),
+            ("colour", "red", Err("unknown field \"colour\"")),
+        ];
+        for (key, value, expected) in cases {
+            let mut task = task();
+            let line = set_field(&mut task, key, value).map(|_| {
+                let field = expected.unwrap_or_default().split(':').next().unwrap_or_default();
+                to_form(&task).lines().find(|line| line.split(':').next() == Some(field)).unwrap_or_default().to_string()
+            });
+            assert_eq!(line, expected.map(String::from).map_err(String::from), "{}:{}", key, value);
+        }
+    }
+
+    #[test]
+    fn form_reads_back_the_same() {
+        clock::set_for_test(clock::parse("2024-06-12 09:00").unwrap());
+        let mut task = task();
+        for (key, value) in [("tz", "Europe/Berlin"), ("due", "2024-06-14 17:30"), ("wait", "2024-06-13"), ("sched", "2024-06-13"),
+            ("pri", "M"), ("tags", "infra ops"), ("contexts", "home"), ("recur", "weekly")] {
+            set_field(&mut task, key, value).unwrap();
+        }
+        let form = to_form(&task);
+        let read = from_form(&task, &form).unwrap();
+        assert_eq!(serde_json::to_string(&read).unwrap(), serde_json::to_string(&task).unwrap());
+        assert_eq!(task.due_at.unwrap().to_rfc3339(), "2024-06-14T17:30:00+02:00");
+
+        let changed = from_form(&task, &form.replace("priority: 2", "priority: L").replace("tags: infra ops", "tags: ops")).unwrap();
+        assert_eq!((changed.priority, changed.tags), (Some(3), vec!["ops".to_string()]));
+        assert_eq!(from_form(&task, "# a comment\n\ndue 2024-06-14").err(), Some("line 3: expected \"field: value\"".to_string()));
+    }
+}
@@ -0,0 +1,218 @@
+//iCalendar export, so vayu's events and tasks show up in other calendar apps.
+//events become VEVENTs and tasks VTODOs, both keyed by their uuid so importing a newer
+//export updates them instead of adding copies. times are floating: they mean the same
+//wall clock time wherever the calendar is opened, like in vayu.
+
+use crate::dates;
+use crate::recur;
+use crate::store::{Event1, Task};
+use crate::workflow;
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, Utc, Weekday};
+use icalendar::{Calendar, Component, DatePerhapsTime, Event, EventLike, Property, Todo, TodoStatus};
+
+pub fn calendar(tasks: &[Task], events: &[Event1], now: DateTime<Local>) -> Calendar {
+    let mut calendar = Calendar::empty();
+    calendar.append_property(Property::new("VERSION", "2.0"));
+    calendar.append_property(Property::new("PRODID", "-//vayu//vayu//EN"));
+    calendar.append_property(Property::new("CALSCALE", "GREGORIAN"));
+    calendar.name("vayu");
+    for event in events {
+        if let Some(event) = event_component(event, now) {
+            calendar.push(event);
+        }
+    }
+    for task in tasks {
+        calendar.push(todo_component(task, now));
+    }
+    calendar.done()
+}
+
+//a one off event on its date, or a weekly one from its next day on.
+//None for a repeat vayu wouldn't show on any day
+fn event_component(event: &Event1, now: DateTime<Local>) -> Option<Event> {
+    let mut component = Event::new();
+    let day = match NaiveDate::parse_from_str(&event.repeat, "%Y-%m-%d") {
+        Ok(date) => date,
+        Err(_) => {
+            let mut days: Vec<Weekday> = event.repeat.split(',').filter_map(dates::weekday).collect();
+            if days.is_empty() {
+                return None;
+            }
+            days.sort_by_key(|day| day.num_days_from_monday());
+            days.dedup();
+            //written out by the recurrence rules tasks use
+            let rule = recur::Rule { freq: recur::Freq::Weekly, interval: 1, by_day: days.clone(), by_month_day: Vec::new(), until: None, count: None };
+            component.add_property("RRULE", &rule.to_string());
+            //the first occurrence from today on, so DTSTART is one of the repeats
+            let today = now.date_naive();
+            (0..7).map(|offset| today + Duration::days(offset))
+                .find(|day| days.contains(&day.weekday()))?
+        },
+    };
+    component
+        .uid(&event.uuid.to_string())
+        .timestamp(now.with_timezone(&Utc))
+        .summary(&escape(&event.description))
+        .starts(day.and_time(event.start))
+        .ends(day.and_time(event.end));
+    Some(component.done())
+}
+
+fn todo_component(task: &Task, now: DateTime<Local>) -> Todo {
+    let mut component = Todo::new();
+    component
+        .uid(&task.uuid.to_string())
+        .timestamp(now.with_timezone(&Utc))
+        .summary(&escape(&task.description));
+    let due: Option<DatePerhapsTime> = match task.due_at {
+        Some(due_at) => Some(due_at.with_timezone(&Utc).into()),
+        None => NaiveDate::parse_from_str(&task.due, "%Y-%m-%d").ok().map(Into::into),
+    };
+    if let Some(due) = due {
+        component.due(due.clone());
+        //a repeat counts from DTSTART, so recurring tasks start on their due date
+        if let Some(rule) = task.recur.as_deref().filter(|_| !task.done).and_then(|rule| recur::parse(rule).ok()) {
+            component.starts(due);
+            component.add_property("RRULE", &rrule(rule, task.due_at.is_some()));
+        }
+    }
+    if let Some(created_at) = task.created_at {
+        component.add_property("CREATED", &created_at.with_timezone(&Utc).format("%Y%m%dT%H%M%SZ").to_string());
+    }
+    if let Some(priority) = task.priority {
+        component.priority(ical_priority(priority));
+    }
+    if !task.tags.is_empty() {
+        let tags: Vec<String> = task.tags.iter().map(|tag| escape(tag)).collect();
+        component.add_property("CATEGORIES", &tags.join(","));
+    }
+    if task.done {
+        component.status(TodoStatus::Completed);
+        if let Some(completed_at) = task.completed_at {
+            component.completed(completed_at.with_timezone(&Utc));
+        }
+    }
+    else if task.is_active() || workflow::state(task) != workflow::first_state() {
+        component.status(TodoStatus::InProcess);
+    }
+    else {
+        component.status(TodoStatus::NeedsAction);
+    }
+    component.done()
+}
+
+//the rule as an RRULE. UNTIL has to be a time too when the start is one, so it's the end of that day
+fn rrule(mut rule: recur::Rule, timed: bool) -> String {
+    let until = rule.until.take();
+    let mut text = rule.to_string();
+    match until {
+        Some(until) if timed => text.push_str(&until.format(";UNTIL=%Y%m%dT235959Z").to_string()),
+        Some(until) => text.push_str(&until.format(";UNTIL=%Y%m%d").to_string()),
+        None => {},
+    }
+    text
+}
+
+//vayu's 1 (most important) to 4 on iCalendar's 1 to 9, where 5 is medium
+fn ical_priority(priority: u8) -> u32 {
+    match priority {
+        1 => 1,
+        2 => 5,
+        3 => 7,
+        _ => 9,
+    }
+}
+
+//TEXT values escape backslashes, commas, semicolons and newlines
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock;
+    use icalendar::parser::{read_calendar, unfold};
+    use serde_json::Map;
+    use uuid::Uuid;
+
+    fn event(description: &str, start: &str, end: &str, repeat: &str) -> Event1 {
+        Event1 {
+            id: 1,
+            uuid: Uuid::new_v4(),
+            description: description.to_string(),
+            start: dates::parse_time(start).unwrap(),
+            end: dates::parse_time(end).unwrap(),
+            repeat: repeat.to_string(),
+            extra: Map::new(),
+        }
+    }
+
+    //each component of the exported file, as its kind and NAME:value lines with the parameters dropped
+    fn export(tasks: &[Task], events: &[Event1]) -> Vec<(String, Vec<String>)> {
+        let text = calendar(tasks, events, clock::parse("2024-06-12 09:00").unwrap()).to_string();
+        let unfolded = unfold(&text);
+        let parsed = read_calendar(&unfolded).unwrap();
+        parsed.components.iter().map(|component| {
+            let lines = component.properties.iter()
+                .filter(|p| !matches!(p.name.as_str(), "UID" | "DTSTAMP" | "CREATED"))
+                .map(|p| format!("{}:{}", p.name.as_str(), p.val.as_str()))
+                .collect();
+            (component.name.as_str().to_string(), lines)
+        }).collect()
+    }
+
+    #[test]
+    fn events_become_vevents() {
+        let events = [
+            event("lab, section 2", "9am", "10:30", "monday,wednesday"),
+            event("brunch", "11am", "noon", "saturday,sunday"),
+            event("dentist", "15:00", "4pm", "2024-06-14"),
+        ];
+        let exported = export(&[], &events);
+        let expected = [
+            vec!["DTEND:20240612T103000", "DTSTART:20240612T090000", "RRULE:FREQ=WEEKLY;BYDAY=MO,WE", "SUMMARY:lab\\, section 2"],
+            vec!["DTEND:20240615T120000", "DTSTART:20240615T110000", "RRULE:FREQ=WEEKLY;BYDAY=SA,SU", "SUMMARY:brunch"],
+            vec!["DTEND:20240614T160000", "DTSTART:20240614T150000", "SUMMARY:dentist"],
+        ];
+        assert_eq!(exported.len(), expected.len());
+        for ((kind, lines), expected) in exported.iter().zip(expected) {
+            assert_eq!(kind, "VEVENT");
+            assert_eq!(lines, &expected);
+        }
+    }
+
+    #[test]
+    fn tasks_become_vtodos() {
+        let mut rent = Task::new(1, "pay rent".to_string(), "2024-07-01".to_string());
+        rent.recur = Some("FREQ=MONTHLY;UNTIL=20241231".to_string());
+        rent.priority = Some(2);
+        rent.tags = vec!["home".to_string()];
+        let mut report = Task::new(2, "report".to_string(), "2024-06-14".to_string());
+        report.due_at = Some(clock::parse("2024-06-14T17:00:00+00:00").unwrap().fixed_offset());
+        report.recur = Some("FREQ=WEEKLY;UNTIL=20240705".to_string());
+        let mut done = Task::new(3, "old".to_string(), String::new());
+        done.done = true;
+        done.completed_at = Some(clock::parse("2024-06-11T08:00:00+00:00").unwrap());
+        done.recur = Some("FREQ=DAILY".to_string());
+        let exported = export(&[rent, report, done], &[]);
+        let expected = [
+            vec!["CATEGORIES:home", "DTSTART:20240701", "DUE:20240701", "PRIORITY:5", "RRULE:FREQ=MONTHLY;UNTIL=20241231", "STATUS:NEEDS-ACTION", "SUMMARY:pay rent"],
+            vec!["DTSTART:20240614T170000Z", "DUE:20240614T170000Z", "RRULE:FREQ=WEEKLY;UNTIL=20240705T235959Z", "STATUS:NEEDS-ACTION", "SUMMARY:report"],
+            vec!["COMPLETED:20240611T080000Z", "STATUS:COMPLETED", "SUMMARY:old"],
+        ];
+        assert_eq!(exported.len(), expected.len());
+        for ((kind, lines), expected) in exported.iter().zip(expected) {
+            assert_eq!(kind, "VTODO");
+            assert_eq!(lines, &expected);
+        }
+    }
+
+    #[test]
+    fn escapes_text() {
+        assert_eq!(escape("a, b; c\\d\ne"), r"a\, b\; c\\d\ne");
+    }
+}
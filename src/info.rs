@@ -0,0 +1,148 @@
+//`vayu info`: everything known about one task, including its notes and the changes made to it
+
+use crate::checklist;
+use crate::clock;
+use crate::config;
+use crate::deps;
+use crate::journal;
+use crate::recur;
+use crate::store::{self, Task};
+use crate::tracking;
+use crate::tree;
+use crate::workflow;
+use crossterm::style::Stylize;
+use std::io;
+use std::path::Path;
+
+fn row(name: &str, value: impl std::fmt::Display) {
+    println!("{:<13} {}", name, value);
+}
+
+pub fn show(dir: &Path, tasks: &[Task], key: &str) -> io::Result<()> {
+    let task = match store::find_task(tasks, key) {
+        Ok(index) => &tasks[index],
+        Err(e) => {
+            println!("{}", e);
+            return Ok(());
+        }
+    };
+    let now = clock::now();
+    println!("{}", format!("task {}", task.id).green());
+    row("uuid", task.uuid);
+    row("description", &task.description);
+    match task.completed_at {
+        Some(completed_at) => row("status", format!("done on {}", completed_at.format("%Y-%m-%d %H:%M"))),
+        None if task.done => row("status", "done"),
+        None if deps::is_blocked(tasks, task) => row("status", "pending, blocked"),
+        None if task.is_waiting(&now.format("%Y-%m-%d").to_string()) => row("status", "pending, waiting"),
+        None => row("status", "pending"),
+    }
+    row("state", workflow::state(task));
+    if !task.due.is_empty() {
+        //a time given in another zone is shown in both
+        match (&task.due_tz, task.due_at) {
+            (Some(zone), Some(due_at)) => row("due", format!("{} ({} {})", task.due_text(), due_at.format("%Y-%m-%d %H:%M"), zone)),
+            _ => row("due", task.due_text()),
+        }
+    }
+    if let Some(wait) = &task.wait {
+        row("wait", wait);
+    }
+    if let Some(scheduled) = &task.scheduled {
+        row("scheduled", scheduled);
+    }
+    if let Some(priority) = task.priority {
+        row("priority", priority);
+    }
+    if !task.tags.is_empty() {
+        row("tags", task.tags.join(" "));
+    }
+    if !task.contexts.is_empty() {
+        row("contexts", task.contexts.join(" "));
+    }
+    if let Some(parent) = task.parent {
+        let description = tasks.iter().find(|t| t.id == parent).map(|t| t.description.as_str()).unwrap_or("not found");
+        row("parent", format!("{} ({})", parent, description));
+    }
+    if let Some((finished, total)) = tree::progress(tasks, task.id) {
+        row("subtasks", format!("{}/{} done", finished, total));
+    }
+    if !task.depends.is_empty() {
+        let depends: Vec<String> = task.depends.iter().map(|id| {
+            match tasks.iter().find(|t| t.id == *id) {
+                Some(t) if t.done => format!("{} (done)", id),
+                Some(_) => id.to_string(),
+                None => format!("{} (not found)", id),
+            }
+        }).collect();
+        row("depends on", depends.join(", "));
+    }
+    if let Some(rule) = task.recur.as_deref().and_then(|rule| recur::parse(rule).ok()) {
+        row("recurs", recur::describe(&rule));
+    }
+    if !task.intervals.is_empty() {
+        let active = if task.is_active() { ", started" } else { "" };
+        row("tracked", format!("{}{}", tracking::hours_minutes(tracking::tracked(task, now)), active));
+    }
+    for (name, _) in config::udas() {
+        if let Some(value) = task.extra.get(name) {
+            row(name, config::show(Some(value)));
+        }
+    }
+    if let Some(created_at) = task.created_at {
+        row("created", created_at.format("%Y-%m-%d %H:%M"));
+    }
+
+    if !task.annotations.is_empty() {
+        println!();
+        println!("{}", "annotations".green());
+        for annotation in &task.annotations {
+            println!("{}  {}", annotation.at.format("%Y-%m-%d %H:%M"), annotation.text);
+        }
+    }
+
+    if let Some((ticked, total)) = checklist::progress(task) {
+        println!();
+        println!("{}", format!("checklist {}/{}", ticked, total).green());
+        checklist::print(task);
+    }
+
+    if !task.transitions.is_empty() {
+        println!();
+        println!("{}", "workflow".green());
+        for transition in &task.transitions {
+            println!("{}  moved to {}", transition.at.format("%Y-%m-%d %H:%M"), transition.state);
+        }
+    }
+
+    let history = journal::task_history(dir, &task.uuid.to_string())?;
+    if !history.is_empty() {
+        println!();
+        println!("{}", "history".green());
+        for (entry, change) in &history {
+            for field in journal::describe_fields(change) {
+                println!("{}  {:<8} {}", entry.at.format("%Y-%m-%d %H:%M"), entry.command, field);
+            }
+        }
+    }
+    Ok(())
+}
+
+//adds a timestamped note to a task
+pub fn annotate(tasks: &mut [Task], key: &str, text: &str) {
+    let index = match store::find_task(tasks, key) {
+        Ok(index) => index,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+    let text = text.trim();
+    if text.is_empty() {
+        println!("annotation can't be empty");
+        return;
+    }
+    let task = &mut tasks[index];
+    task.annotations.push(store::Annotation { at: clock::now(), text: text.to_string() });
+    println!("annotated task {}", task.id);
+}
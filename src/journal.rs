@@ -0,0 +1,495 @@
+//append only log of every change made to the stores.
+//each command that changes something adds one entry holding the before and after state of
+//every record it touched, which is enough to undo and redo it later.
+//undo and redo are entries too, pointing back at the entry they undid or redid.
+
+use crate::clock;
+use crate::store::{Access, Store, Task, Event1};
+use uuid::Uuid;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+pub const JOURNAL_FILE: &str = "journal.jsonl";
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Change {
+    //"task" or "event"
+    pub kind: String,
+    //the id at the time, for showing. records are found by uuid, or by id for entries written before it was kept
+    pub id: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<Uuid>,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub seq: u64,
+    pub at: DateTime<Local>,
+    pub command: String,
+    #[serde(default)]
+    pub changes: Vec<Change>,
+    //for undo and redo entries, the seq of the entry that was undone or redone
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<u64>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+//state of every record in the store keyed by (kind, uuid)
+pub type Snapshot = BTreeMap<(String, Uuid), Value>;
+
+pub fn snapshot(store: &Store) -> Snapshot {
+    let mut snap = Snapshot::new();
+    for task in &store.tasks {
+        snap.insert(("task".to_string(), task.uuid), serde_json::to_value(task).unwrap_or(Value::Null));
+    }
+    for event in &store.events {
+        snap.insert(("event".to_string(), event.uuid), serde_json::to_value(event).unwrap_or(Value::Null));
+    }
+    snap
+}
+
+//every record that was added, removed or changed between the two snapshots
+pub fn diff(before: &Snapshot, after: &Snapshot) -> Vec<Change> {
+    let mut changes = Vec::new();
+    for (key, old) in before {
+        match after.get(key) {
+            Some(new) if new == old => {},
+            new => changes.push(change(key, Some(old.clone()), new.cloned())),
+        }
+    }
+    for (key, new) in after {
+        if !before.contains_key(key) {
+            changes.push(change(key, None, Some(new.clone())));
+        }
+    }
+    changes
+}
+
+fn change(key: &(String, Uuid), before: Option<Value>, after: Option<Value>) -> Change {
+    let id = after.as_ref().or(before.as_ref()).and_then(|v| v.get("id")).and_then(|id| id.as_i64()).unwrap_or(0);
+    Change {
+        kind: key.0.clone(),
+        id: id as i32,
+        uuid: Some(key.1),
+        before,
+        after,
+        extra: Map::new(),
+    }
+}
+
+//the same change the other way around
+fn reverse(change: &Change) -> Change {
+    Change {
+        before: change.after.clone(),
+        after: change.before.clone(),
+        ..change.clone()
+    }
+}
+
+pub fn read(dir: &Path) -> io::Result<Vec<Entry>> {
+    let (entries, warnings) = read_quietly(dir)?;
+    for warning in warnings {
+        println!("{}", warning);
+    }
+    Ok(entries)
+}
+
+//the entries and a warning for each line that couldn't be read
+fn read_quietly(dir: &Path) -> io::Result<(Vec<Entry>, Vec<String>)> {
+    let contents = match fs::read_to_string(dir.join(JOURNAL_FILE)) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok((Vec::new(), Vec::new())),
+        Err(e) => return Err(e),
+    };
+    let mut entries = Vec::new();
+    let mut warnings = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(line) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => warnings.push(format!("warning: skipping bad journal entry on line {}: {}", i + 1, e)),
+        }
+    }
+    Ok((entries, warnings))
+}
+
+//appends one entry to the journal. the caller must hold the store lock.
+//bad lines are left for undo and `vayu log` to warn about, this also runs under the tui
+pub fn record(dir: &Path, command: &str, changes: Vec<Change>, target: Option<u64>) -> io::Result<Entry> {
+    let seq = read_quietly(dir)?.0.last().map(|e| e.seq).unwrap_or(0) + 1;
+    let entry = Entry {
+        seq,
+        at: clock::now(),
+        command: command.to_string(),
+        changes,
+        target,
+        extra: Map::new(),
+    };
+    let mut line = serde_json::to_vec(&entry)?;
+    line.push(b'\n');
+    let mut file = fs::OpenOptions::new().create(true).append(true).open(dir.join(JOURNAL_FILE))?;
+    file.write_all(&line)?;
+    file.sync_all()?;
+    Ok(entry)
+}
+
+//writes the store back and journals what changed since `before` was taken
+pub fn save_and_record(store: &mut Store, before: &Snapshot, command: &str) -> io::Result<()> {
+    store.save()?;
+    let changes = diff(before, &snapshot(store));
+    if !changes.is_empty() {
+        record(&store.dir, command, changes, None)?;
+    }
+    Ok(())
+}
+
+//loads the store for writing, lets `change` edit it, then saves and journals what changed.
+//commands that ask something first ask it on a store loaded for reading, without holding the lock,
+//and make the change here on what's on disk once it has been answered
+pub fn update(dir: &Path, command: &str, change: impl FnOnce(&mut Store)) -> io::Result<()> {
+    let mut store = Store::load(dir, Access::Write)?;
+    let before = snapshot(&store);
+    change(&mut store);
+    save_and_record(&mut store, &before, command)
+}
+
+//replays the journal to find which entries can currently be undone and redone.
+//a new change clears the redo stack, the same as in an editor.
+fn stacks(entries: &[Entry]) -> (Vec<&Entry>, Vec<&Entry>) {
+    let by_seq: BTreeMap<u64, &Entry> = entries.iter().map(|e| (e.seq, e)).collect();
+    let mut undo: Vec<&Entry> = Vec::new();
+    let mut redo: Vec<&Entry> = Vec::new();
+    for entry in entries {
+        match (entry.command.as_str(), entry.target.and_then(|t| by_seq.get(&t))) {
+            ("undo", Some(target)) => {
+                undo.retain(|e| e.seq != target.seq);
+                redo.push(target);
+            },
+            ("redo", Some(target)) => {
+                redo.retain(|e| e.seq != target.seq);
+                undo.push(target);
+            },
+            ("undo", None) | ("redo", None) => {},
+            _ => {
+                undo.push(entry);
+                redo.clear();
+            }
+        }
+    }
+    (undo, redo)
+}
+
+//undoes the latest change that can be undone and saves the store. the journal entry is only
+//written once the save worked, so it never claims a change that didn't happen
+pub fn undo(store: &mut Store) -> io::Result<Option<Entry>> {
+    let entries = read(&store.dir)?;
+    let target = match stacks(&entries).0.last() {
+        Some(target) => (*target).clone(),
+        None => return Ok(None),
+    };
+    for change in &target.changes {
+        check(store, change, &change.after)?;
+    }
+    //walk the changes backwards, putting each record back how it was
+    let applied: Vec<Change> = target.changes.iter().rev().map(reverse).collect();
+    for change in &applied {
+        apply(store, change, &change.after)?;
+    }
+    store.save()?;
+    //archived tasks are back in the task store, so they come out of the archive after it's saved
+    if target.command == "archive" {
+        store.remove_from_archive(&archived(&target))?;
+    }
+    record(&store.dir, "undo", applied, Some(target.seq))?;
+    Ok(Some(target))
+}
+
+pub fn redo(store: &mut Store) -> io::Result<Option<Entry>> {
+    let entries = read(&store.dir)?;
+    let target = match stacks(&entries).1.last() {
+        Some(target) => (*target).clone(),
+        None => return Ok(None),
+    };
+    for change in &target.changes {
+        check(store, change, &change.before)?;
+    }
+    //like `vayu archive`, the archive is written before the tasks leave the task store
+    if target.command == "archive" {
+        let tasks: Vec<Task> = store.tasks.iter().filter(|t| archived(&target).contains(&t.uuid)).cloned().collect();
+        store.add_to_archive(&tasks)?;
+    }
+    let applied = target.changes.clone();
+    for change in &applied {
+        apply(store, change, &change.after)?;
+    }
+    store.save()?;
+    record(&store.dir, "redo", applied, Some(target.seq))?;
+    Ok(Some(target))
+}
+
+//uuids of the tasks an archive entry moved to archive.jsonl
+fn archived(entry: &Entry) -> Vec<Uuid> {
+    entry.changes.iter().filter(|c| c.kind == "task" && c.after.is_none()).filter_map(|c| c.uuid).collect()
+}
+
+//whether a record is the one a change is about
+fn is_target(change: &Change, id: i32, uuid: Uuid) -> bool {
+    match change.uuid {
+        Some(target) => target == uuid,
+        None => id == change.id,
+    }
+}
+
+//makes sure a record is still in the state the journal expects. if it was changed outside
+//the journal since (for example by an older vayu) it is left alone rather than silently overwritten.
+fn check(store: &Store, change: &Change, expected: &Option<Value>) -> io::Result<()> {
+    let current = match change.kind.as_str() {
+        "task" => store.tasks.iter().find(|t| is_target(change, t.id, t.uuid)).map(serde_json::to_value),
+        "event" => store.events.iter().find(|e| is_target(change, e.id, e.uuid)).map(serde_json::to_value),
+        _ => None,
+    }.transpose()?;
+    if &current != expected {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} {} has changed since, it can't be restored automatically", change.kind, change.id),
+        ));
+    }
+    Ok(())
+}
+
+//puts one record into the state `to`, removing it when `to` is None
+fn apply(store: &mut Store, change: &Change, to: &Option<Value>) -> io::Result<()> {
+    match change.kind.as_str() {
+        "task" => {
+            let record = to.clone().map(serde_json::from_value::<Task>).transpose()?;
+            let position = store.tasks.iter().position(|t| is_target(change, t.id, t.uuid));
+            put(&mut store.tasks, position, record);
+        },
+        "event" => {
+            let record = to.clone().map(serde_json::from_value::<Event1>).transpose()?;
+            let position = store.events.iter().position(|e| is_target(change, e.id, e.uuid));
+            put(&mut store.events, position, record);
+        },
+        kind => {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("unknown record kind {} in journal", kind)));
+        }
+    }
+    Ok(())
+}
+
+//a changed record stays where it was in the list, a new one goes at the end
+fn put<T>(records: &mut Vec<T>, position: Option<usize>, record: Option<T>) {
+    match (position, record) {
+        (Some(i), Some(record)) => records[i] = record,
+        (Some(i), None) => {
+            records.remove(i);
+        },
+        (None, Some(record)) => records.push(record),
+        (None, None) => {},
+    }
+}
+
+//every change made to the task with this uuid, oldest first
+pub fn task_history(dir: &Path, uuid: &str) -> io::Result<Vec<(Entry, Change)>> {
+    let mut history = Vec::new();
+    for entry in read(dir)? {
+        for change in &entry.changes {
+            let matches = |value: &Option<Value>| value.as_ref().and_then(|v| v.get("uuid")).and_then(|u| u.as_str()) == Some(uuid);
+            if change.kind == "task" && (matches(&change.before) || matches(&change.after)) {
+                history.push((entry.clone(), change.clone()));
+            }
+        }
+    }
+    Ok(history)
+}
+
+//the fields a change touched, e.g. "due: 2024-06-01 -> 2024-06-07"
+pub fn describe_fields(change: &Change) -> Vec<String> {
+    let (before, after) = match (&change.before, &change.after) {
+        (None, Some(_)) => return vec!["created".to_string()],
+        (Some(_), None) => return vec!["removed".to_string()],
+        (Some(Value::Object(before)), Some(Value::Object(after))) => (before, after),
+        _ => return Vec::new(),
+    };
+    let mut keys: Vec<&String> = before.keys().chain(after.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    keys.into_iter().filter(|key| before.get(*key) != after.get(*key)).map(|key| {
+        let (old, new) = (before.get(key), after.get(key));
+        let count = |value: Option<&Value>| value.and_then(|v| v.as_array()).map_or(0, |items| items.len());
+        let records = |value: Option<&Value>| value.and_then(|v| v.as_array()).is_some_and(|items| items.iter().any(|i| i.is_object()));
+        //lists of records like annotations are summed up rather than printed whole
+        if records(old) || records(new) {
+            return match (count(old), count(new)) {
+                (old, new) if old == new => format!("{}: changed", key),
+                (old, new) => format!("{}: {} -> {} entries", key, old, new),
+            };
+        }
+        format!("{}: {} -> {}", key, show(old), show(new))
+    }).collect()
+}
+
+fn show(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => "(none)".to_string(),
+        Some(Value::String(text)) if text.is_empty() => "(none)".to_string(),
+        Some(Value::String(text)) => shorten(text),
+        Some(Value::Array(items)) if items.is_empty() => "(none)".to_string(),
+        Some(Value::Array(items)) => shorten(&items.iter().map(|i| i.as_str().map_or(i.to_string(), String::from)).collect::<Vec<_>>().join(" ")),
+        Some(value) => shorten(&value.to_string()),
+    }
+}
+
+fn shorten(text: &str) -> String {
+    if text.chars().count() > 40 {
+        format!("{}...", text.chars().take(37).collect::<String>())
+    }
+    else {
+        text.to_string()
+    }
+}
+
+//one line summary of an entry for `vayu log`, e.g. "added task 4 (buy milk)"
+pub fn describe(entry: &Entry) -> String {
+    let parts: Vec<String> = entry.changes.iter().map(|change| {
+        let verb = match (&change.before, &change.after) {
+            (Some(_), None) if entry.command == "archive" => "archived",
+            (None, Some(_)) => "added",
+            (Some(_), None) => "removed",
+            (Some(before), Some(after)) if before.get("done") != after.get("done") => {
+                if after.get("done") == Some(&Value::Bool(true)) { "completed" } else { "reopened" }
+            },
+            _ => "changed",
+        };
+        let description = change.after.as_ref().or(change.before.as_ref())
+            .and_then(|v| v.get("description"))
+            .and_then(|d| d.as_str())
+            .unwrap_or("");
+        format!("{} {} {} ({})", verb, change.kind, change.id, description.trim())
+    }).collect();
+    match entry.target {
+        Some(target) => format!("#{}: {}", target, parts.join(", ")),
+        None => parts.join(", "),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store;
+
+    fn descriptions(store: &Store) -> Vec<String> {
+        store.tasks.iter().map(|t| t.description.clone()).collect()
+    }
+
+    type Step = fn(&mut Store) -> io::Result<Option<Entry>>;
+
+    //runs `change` on the store as the command `command` would, saving and journaling it
+    fn run(store: &mut Store, command: &str, change: impl FnOnce(&mut Store)) {
+        let before = snapshot(store);
+        change(store);
+        save_and_record(store, &before, command).unwrap();
+    }
+
+    #[test]
+    fn undo_and_redo_replay_the_journal() {
+        let dir = store::temp_dir("journal");
+        let mut store = Store::load(&dir, Access::Write).unwrap();
+        run(&mut store, "add", |s| s.tasks.push(Task::new(1, "buy milk".to_string(), String::new())));
+        run(&mut store, "add", |s| s.tasks.push(Task::new(2, "walk dog".to_string(), String::new())));
+        run(&mut store, "modify", |s| s.tasks[0].description = "buy oat milk".to_string());
+        assert_eq!(descriptions(&store), ["buy oat milk", "walk dog"]);
+
+        drop(store);
+
+        let steps = [
+            (undo as Step, Some("modify"), vec!["buy milk", "walk dog"]),
+            (undo, Some("add"), vec!["buy milk"]),
+            (redo, Some("add"), vec!["buy milk", "walk dog"]),
+            (redo, Some("modify"), vec!["buy oat milk", "walk dog"]),
+            (redo, None, vec!["buy oat milk", "walk dog"]),
+            (undo, Some("modify"), vec!["buy milk", "walk dog"]),
+        ];
+        //each step is its own run of vayu, so it only sees what the step before saved
+        for (i, (step, command, expected)) in steps.into_iter().enumerate() {
+            let mut store = Store::load(&dir, Access::Write).unwrap();
+            let entry = step(&mut store).unwrap();
+            assert_eq!(entry.map(|e| e.command).as_deref(), command, "step {}", i);
+            assert_eq!(descriptions(&store), expected, "step {}", i);
+        }
+        //a new change clears what could be redone
+        let mut store = Store::load(&dir, Access::Write).unwrap();
+        run(&mut store, "modify", |s| s.tasks[0].description = "buy soy milk".to_string());
+        assert!(redo(&mut store).unwrap().is_none());
+        assert_eq!(undo(&mut store).unwrap().unwrap().command, "modify");
+        assert_eq!(descriptions(&store), ["buy milk", "walk dog"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn changes_follow_the_uuid_not_the_id() {
+        let dir = store::temp_dir("journal-uuid");
+        let mut store = Store::load(&dir, Access::Write).unwrap();
+        run(&mut store, "add", |s| {
+            s.tasks.push(Task::new(1, "first".to_string(), String::new()));
+            s.tasks.push(Task::new(2, "second".to_string(), String::new()));
+        });
+        //the two tasks swap ids, ex: by hand in `vayu edit`
+        run(&mut store, "edit", |s| {
+            s.tasks[0].id = 2;
+            s.tasks[1].id = 1;
+        });
+        let entry = read(&dir).unwrap().pop().unwrap();
+        let uuids: Vec<Option<Uuid>> = entry.changes.iter().map(|c| c.uuid).collect();
+        assert_eq!(uuids.len(), 2);
+        assert!(uuids.iter().all(|uuid| uuid.is_some_and(|uuid| store.tasks.iter().any(|t| t.uuid == uuid))));
+        run(&mut store, "modify", |s| s.tasks[0].description = "first, edited".to_string());
+
+        assert_eq!(undo(&mut store).unwrap().unwrap().command, "modify");
+        assert_eq!(undo(&mut store).unwrap().unwrap().command, "edit");
+        let tasks: Vec<(i32, &str)> = store.tasks.iter().map(|t| (t.id, t.description.as_str())).collect();
+        assert_eq!(tasks, [(1, "first"), (2, "second")]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn undoing_an_archive_brings_the_tasks_back() {
+        let dir = store::temp_dir("journal-archive");
+        let mut store = Store::load(&dir, Access::Write).unwrap();
+        run(&mut store, "add", |s| {
+            let mut done = Task::new(1, "old".to_string(), String::new());
+            done.done = true;
+            s.tasks.push(done);
+            s.tasks.push(Task::new(2, "open".to_string(), String::new()));
+        });
+        run(&mut store, "archive", |s| {
+            s.archive_tasks(clock::now()).unwrap();
+        });
+        let archived = |dir: &Path| store::load_archive(dir).unwrap().iter().map(|t| t.description.clone()).collect::<Vec<_>>();
+        assert_eq!(descriptions(&store), ["open"]);
+        assert_eq!(archived(&dir), ["old"]);
+
+        assert_eq!(undo(&mut store).unwrap().unwrap().command, "archive");
+        assert_eq!(descriptions(&store), ["open", "old"]);
+        assert!(archived(&dir).is_empty());
+
+        assert_eq!(redo(&mut store).unwrap().unwrap().command, "archive");
+        assert_eq!(descriptions(&store), ["open"]);
+        assert_eq!(archived(&dir), ["old"]);
+        drop(store);
+        assert_eq!(descriptions(&Store::load(&dir, Access::Read).unwrap()), ["open"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -2,21 +2,19 @@ extern crate clap;
 extern crate colored;
 extern crate chrono;
 extern crate indicatif;
-extern crate date_time_parser;
 
 //date parsers.
-use date_time_parser::DateParser;
+mod dates;
 
 //tui
-use std::io::{self, stdout, BufRead, Write};
+use std::io::{self, stdout};
 use crossterm::{
     event::{self, Event as UIEvent, KeyCode}, style::Stylize, terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen}, ExecutableCommand
 };
 use ratatui::{prelude::*, widgets::*};
-use clap::Parser;
-use std::fs::File;
-use std::path::Path;
-use chrono::{Local,Duration};
+use clap::{CommandFactory, Parser};
+use std::path::PathBuf;
+use chrono::{DateTime, Local, Duration, NaiveDate};
 //use std::io::prelude::*;
 //use chrono::Datelike;
 use indicatif::ProgressBar;
@@ -35,6 +33,29 @@ use tokio::io::{stdout as tokiostdout, AsyncWriteExt};
 //summarizer dependencies
 use std::fs as fs;
 
+//task and event storage
+mod board;
+mod checklist;
+mod clock;
+mod config;
+mod deps;
+mod doctor;
+mod edit;
+mod ics;
+mod info;
+mod journal;
+mod paths;
+mod query;
+mod recur;
+mod store;
+mod tracking;
+mod tree;
+mod urgency;
+mod workflow;
+use store::{Access, Event1, Store, Task};
+use serde_json::Map;
+use uuid::Uuid;
+
 
 
 //rewrite for CLI parser using subcommand feature
@@ -44,8 +65,11 @@ enum SubComm {
     News{
     },
     Add{
-        /// task description and due date (YYYY-MM-DD or today,yesterday,monday,etc.) separated by a colon. Ex: "vayu add yoga due:today"
+        /// task description and due date (YYYY-MM-DD or today,yesterday,monday,etc.) separated by a colon, optionally with a priority (H,M,L or 1-4), +tags, @contexts and depends:<id>. Ex: "vayu add yoga due:today priority:H +health @home"
         arg1: String,
+        /// make this a subtask of another task (id or uuid prefix). Ex: vayu add --parent 7 "write tests due:friday"
+        #[clap(long)]
+        parent: Option<String>,
     },
     Ask{
         /// question to ask to phi llm. Ex: vayu ask "what is the goal of rust"
@@ -56,13 +80,45 @@ enum SubComm {
         arg1: String,
     },
     Auto{
-        /// auto generate a task. Ex: vayu auto "test at end of month"
+        /// auto generate a task, optionally with a priority, +tags and @contexts. Ex: vayu auto "test at end of month priority:H +school"
         arg1: String,
     },
     List{
+        /// show completed tasks instead of pending ones
+        #[clap(long)]
+        done: bool,
+        /// show pending, waiting and completed tasks
+        #[clap(long)]
+        all: bool,
+        /// order of the list: due, scheduled, urgency, priority, id, or an attribute from config.toml. urgency puts what to work on next at the top, scheduled orders by planned start
+        #[clap(long, default_value = "due")]
+        sort: String,
+        /// highlight tasks due within this many hours, as well as tasks due today
+        #[clap(long, default_value = "24")]
+        soon: i64,
+        /// only show tasks matching this query. Ex: vayu list +infra -@home, vayu list 'due.before:friday and not priority:L'
+        #[clap(allow_hyphen_values = true)]
+        filter: Vec<String>,
     },
     Done{
-        /// task id. Ex: "vayu done 1"
+        /// task id, a unique prefix of its uuid, or a query to complete every matching task. Ex: "vayu done 1", vayu done 'due.before:today and +chore'
+        arg1: String,
+    },
+    Move{
+        /// task id, uuid prefix, or a query to move every matching task
+        arg1: String,
+        /// workflow state to move to, from the states in config.toml (todo, doing, done by default). the last state completes the task. Ex: vayu move 4 doing
+        state: String,
+    },
+    Modify{
+        /// task id, uuid prefix, or a query to change every matching task
+        arg1: String,
+        /// changes to make. field:value sets a field (due, priority, depends), +tag/@context adds one and -tag/-@context removes it, any other text becomes the new description. Ex: vayu modify 4 due:friday +infra "new text"
+        #[clap(required = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+    Edit{
+        /// task id or uuid prefix. opens the task in $EDITOR
         arg1: String,
     },
     Pomo{
@@ -76,11 +132,11 @@ enum SubComm {
     Eadd{
         /// event description
         arg1: String,
-        /// event start time (H:MMam or H:MMpm)
+        /// event start time (5:30pm, 5pm or 17:30)
         arg2: String,
-        /// event end time (H:MMam or H:MMpm)
+        /// event end time (5:30pm, 5pm or 17:30)
         arg3: String,
-        /// event repeat (day1,day2,day3,day4,day5,day6,day7 where dayi is a day of the week) or (YYYY-MM-DD) or (everyday,weekday,weekend)
+        /// event repeat (day1,day2,day3,day4,day5,day6,day7 where dayi is a day of the week) or a date (YYYY-MM-DD, tomorrow, next friday) or (everyday,weekday,weekend)
         arg4: String,
     },
     Elist{
@@ -88,49 +144,102 @@ enum SubComm {
     Eids{
     },
     Erem{
-        /// event id to remove
+        /// event id or a unique prefix of its uuid to remove
+        arg1: String,
+    },
+    Init{
+    },
+    Migrate{
+        /// directory with the tasks.txt and events.txt of an older vayu, usually where it was run from. Ex: vayu migrate ~/notes
+        #[clap(default_value = ".")]
+        dir: PathBuf,
+    },
+    Archive{
+        /// archive tasks completed at least this many days ago
+        #[clap(long, default_value = "14")]
+        days: i64,
+    },
+    Doctor{
+        /// move bad records into a .rejected file next to the store
+        #[clap(long)]
+        fix: bool,
+    },
+    Undo{
+    },
+    Redo{
+    },
+    Log{
+        /// number of operations to show
+        #[clap(long, short, default_value = "10")]
+        number: usize,
+    },
+    Graph{
+    },
+    Annotate{
+        /// task id or uuid prefix
         arg1: String,
+        /// the note to attach. Ex: vayu annotate 4 "talked to ops, waiting on ticket"
+        #[clap(required = true)]
+        text: Vec<String>,
+    },
+    Check{
+        /// task id or uuid prefix, or "add" to add an item. Ex: vayu check add 4 "bump version", vayu check 4 2 3
+        arg1: String,
+        /// numbers of the items to tick (leave out to show the checklist), or after "add" the task and the item's text
+        args: Vec<String>,
+        /// untick the items instead
+        #[clap(long)]
+        untick: bool,
+    },
+    Info{
+        /// task id or uuid prefix. shows the whole task with its notes and history
+        arg1: String,
+    },
+    Start{
+        /// task id or uuid prefix to track time against. stops any other started task
+        arg1: String,
+    },
+    Stop{
+    },
+    Report{
+        /// which report to show
+        #[clap(value_parser = ["time"])]
+        kind: String,
+        /// only count time tracked this week (from monday)
+        #[clap(long)]
+        week: bool,
+        /// print every piece of tracked work as csv instead of totals
+        #[clap(long)]
+        csv: bool,
+    },
+    Export{
+        /// output format: json for tasks, ics for events and tasks as an iCalendar file
+        #[clap(value_parser = ["json", "ics"])]
+        format: String,
+        /// only export tasks matching this query, events are always included. Ex: vayu export json +work
+        #[clap(allow_hyphen_values = true)]
+        filter: Vec<String>,
     },
 }
 
 //struct for the main command.
+//its own help flag is off so that `vayu add --help` reaches the help of add, see `help` below
 #[derive(Parser)]
+#[clap(disable_help_flag = true)]
 struct Arguments {
     #[clap(default_value = "")]
     command: String,
-    #[clap(default_value = "")]
-    arg1: String,
-    #[clap(default_value = "")]
-    arg2: String,
-    #[clap(default_value = "")]
-    arg3: String,
-    #[clap(default_value = "")]
-    arg4: String,
+    /// arguments for the command. use "vayu <command> --help" to see them
+    #[clap(allow_hyphen_values = true, trailing_var_arg = true)]
+    args: Vec<String>,
+    /// directory for the task and event stores. defaults to VAYU_HOME, the nearest .vayu/ directory, or $XDG_DATA_HOME/vayu. must come before the command
+    #[clap(long)]
+    data_dir: Option<PathBuf>,
+    /// print help. after a command, print the help of that command
+    #[clap(short, long)]
+    help: bool,
 }
 
-//struct for a task. there are some weird warnings about this being unused
-#[allow(dead_code)]
-#[derive(Clone)]
-struct Task {
-    description: String,
-    due: String,
-    done: bool,
-    id: i32,
-}
-
-#[allow(dead_code)]
-#[derive(Clone)]
-struct Event1 {
-    description: String,
-    start: String,
-    end: String,
-    repeat: String,
-    id: i32,
-}
-
-
-
-
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let ollama = Ollama::default();
@@ -139,85 +248,79 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let mut _context: Option<GenerationContext> = None;
     
-    let mut next_id = 0;
-
-    let file = File::open("tasks.txt");
-    //if the file doesn't exist, create it
-    if file.is_err() {
-        File::create("tasks.txt").expect("Unable to create file");
+    //CLI PARSING
+    let matches = Arguments::parse();
+    if matches.help {
+        if matches.command.is_empty() {
+            Arguments::command().print_help()?;
+            return Ok(());
+        }
+        //the subcommand parser prints the command's help and exits
+        let args = match matches.command.as_str() {
+            "list" => list_flags_first(paths::subcommand_args()),
+            _ => paths::subcommand_args(),
+        };
+        SubComm::parse_from(args);
     }
 
-    let file2 = File::open("events.txt");
-    //if the file doesn't exist, create it
-    if file2.is_err() {
-        File::create("events.txt").expect("Unable to create file");
+    //VAYU_NOW can move vayu to another moment, for checking how things look on a given day
+    match clock::from_env() {
+        Ok(clock) => clock::install(clock),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        }
     }
 
-
-    //reads the task list data into a vector of tasks. (this is used by task list and for updating the file after adding a task)
-    let mut tasks : Vec<Task> = Vec::new();
-    if let Ok(lines) = read_lines("tasks.txt") {
-        // Consumes the iterator, returns an (Optional) String
-        for line in lines {
-            if let Ok(task) = line {
-                let task_vec : Vec<&str> = task.split("%").collect();
-                let task_id = task_vec[3].to_string().parse::<i32>().unwrap();
-                let task = Task {
-                    description: task_vec[0].to_string(),
-                    due: task_vec[1].to_string(),
-                    done: task_vec[2].to_string().parse::<bool>().unwrap(),
-                    id: task_id,
-                };
-                if task_id > next_id {
-                    next_id = task_id;
-                }
-                tasks.push(task);
-            }
-        }
+    //reads the task and event stores. (this is used by the list commands and for updating the stores after a change)
+    //commands that change the stores keep them locked until they have been written back.
+    //auto, done, move and modify can ask for confirmation, so they read first and take the lock once answered
+    let access = match matches.command.as_str() {
+        "add" | "annotate" | "check" | "start" | "stop" | "eadd" | "erem" | "archive" | "migrate" | "undo" | "redo" => Access::Write,
+        _ => Access::Read,
+    };
+    let data_dir = paths::data_dir(matches.data_dir)?;
+    //attributes declared in config.toml are needed to check and edit tasks
+    if let Err(e) = config::load(&data_dir) {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
     }
-    //next id to be used is one higher than the highest id in the task list
-    next_id += 1;
-
-    let mut next_event_id = 0;
-
-    //reads the event list data into a vector of events. (this is used by event list and for updating the file after adding an event)
-    let mut events : Vec<Event1> = Vec::new();
-    if let Ok(lines) = read_lines("events.txt") {
-        // Consumes the iterator, returns an (Optional) String
-        for line in lines {
-            if let Ok(event) = line {
-                let event_vec : Vec<&str> = event.split("%").collect();
-                let event_id = event_vec[4].to_string().parse::<i32>().unwrap();
-                let event = Event1 {
-                    description: event_vec[0].to_string(),
-                    start: event_vec[1].to_string(),
-                    end: event_vec[2].to_string(),
-                    repeat: event_vec[3].to_string(),
-                    id: event_id,
-                };
-                if event_id > next_event_id {
-                    next_event_id = event_id;
+    //doctor reads the raw files itself, before loading skips anything it can't use
+    if matches.command == "doctor" {
+        let submatches = SubComm::parse_from(paths::subcommand_args());
+        match submatches {
+            SubComm::Doctor{fix} => {
+                if let Err(e) = doctor::run(&data_dir, fix) {
+                    eprintln!("error: {}", e);
+                    std::process::exit(1);
                 }
-                events.push(event);
+            },
+            _ => {
+                println!("invalid usage of doctor. use --help to see usage");
             }
         }
+        return Ok(());
     }
+    let mut store = match Store::load(&data_dir, access) {
+        Ok(store) => store,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    //everything a command changes is recorded in the journal so it can be undone
+    let before = journal::snapshot(&store);
+    let next_id = store.next_task_id();
+    let next_event_id = store.next_event_id();
 
-    //next id to be used is one higher than the highest id in the event list
-    next_event_id += 1;
-
-
-
-    //CLI PARSING
-    let matches = Arguments::parse();
     match matches.command.as_str() {
         //use the subcommands so that --help works for each subcommand
         //we want to see the internal comments for each subcommand so we can't use the macro
         "add" => {
-            let submatches = SubComm::parse();
+            let submatches = SubComm::parse_from(paths::subcommand_args());
             match submatches {
-                SubComm::Add{arg1} => {
-                    add_task(&mut tasks, next_id, arg1);
+                SubComm::Add{arg1, parent} => {
+                    add_task(&mut store.tasks, next_id, arg1, parent);
                 },
                 _ => {
                     println!("invalid usage of add. use --help to see usage");
@@ -225,7 +328,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
         },
         "summary" =>{
-            let submatches = SubComm::parse();
+            let submatches = SubComm::parse_from(paths::subcommand_args());
             match submatches {
                 SubComm::Summary{arg1} => {
                     if !arg1.ends_with(".txt"){
@@ -269,7 +372,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
         }
         "ask" => {
-            let submatches = SubComm::parse();
+            let submatches = SubComm::parse_from(paths::subcommand_args());
             match submatches {
                 SubComm::Ask{arg1} => {
                     let input = arg1.to_string();
@@ -277,7 +380,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     let sys_prompt = "If the user asks about anything task related use the following list of tasks as context: ";
                     //combine all tasks into a context string
                     let mut context = "".to_string();
-                    for task in &tasks {
+                    for task in store.tasks.iter().filter(|task| !task.done) {
                         context.push_str(&task.description);
                         context.push_str(", Deadline: ");
                         context.push_str(&task.due);
@@ -314,10 +417,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
         },
         "auto" => {
-            let submatches = SubComm::parse();
+            let submatches = SubComm::parse_from(paths::subcommand_args());
             match submatches {
                 SubComm::Auto{arg1} => {
-                    add_auto(&mut tasks, next_id, arg1);
+                    add_auto(&store.dir, arg1)?;
                 },
                 _ => {
                     println!("invalid usage of auto. use --help to see usage");
@@ -325,7 +428,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
         }
         "news" => {
-            let submatches = SubComm::parse();
+            let submatches = SubComm::parse_from(paths::subcommand_args());
             match submatches {
                 SubComm::News{} => {
                     news();
@@ -336,10 +439,18 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
         },
         "list" => {
-            let submatches = SubComm::parse();
+            let submatches = SubComm::parse_from(list_flags_first(paths::subcommand_args()));
             match submatches {
-                SubComm::List{} => {
-                    list_tasks(&mut tasks);
+                SubComm::List{done, all, sort, soon, filter} => {
+                    let sorts = ["due", "scheduled", "urgency", "priority", "id"];
+                    if !sorts.contains(&sort.as_str()) && config::uda(&sort).is_none() {
+                        println!("can't sort by \"{}\". use due, scheduled, urgency, priority, id or an attribute from {}", sort, config::CONFIG_FILE);
+                        return Ok(());
+                    }
+                    match query::Query::parse(&filter) {
+                        Ok(filter) => list_tasks(&mut store.tasks, done, all, &sort, soon, &filter),
+                        Err(e) => println!("{}", e),
+                    }
                 },
                 _ => {
                     println!("invalid usage of list. use --help to see usage");
@@ -347,18 +458,51 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
         },
         "done" => {
-            let submatches = SubComm::parse();
+            let submatches = SubComm::parse_from(paths::subcommand_args());
             match submatches {
                 SubComm::Done{arg1} => {
-                    remove_task(&mut tasks, arg1);
+                    complete_tasks(&store.dir, &store.tasks, arg1)?;
                 },
                 _ => {
                     println!("invalid usage of done. use --help to see usage");
                 }
             }
         },
+        "move" => {
+            let submatches = SubComm::parse_from(paths::subcommand_args());
+            match submatches {
+                SubComm::Move{arg1, state} => {
+                    move_tasks(&store.dir, &store.tasks, arg1, &state)?;
+                },
+                _ => {
+                    println!("invalid usage of move. use --help to see usage");
+                }
+            }
+        },
+        "modify" => {
+            let submatches = SubComm::parse_from(paths::subcommand_args());
+            match submatches {
+                SubComm::Modify{arg1, args} => {
+                    modify_tasks(&store.dir, &store.tasks, arg1, args)?;
+                },
+                _ => {
+                    println!("invalid usage of modify. use --help to see usage");
+                }
+            }
+        },
+        "edit" => {
+            let submatches = SubComm::parse_from(paths::subcommand_args());
+            match submatches {
+                SubComm::Edit{arg1} => {
+                    edit::edit(&store.dir, &store.tasks, &arg1)?;
+                },
+                _ => {
+                    println!("invalid usage of edit. use --help to see usage");
+                }
+            }
+        },
         "pomo" => {
-            let submatches = SubComm::parse();
+            let submatches = SubComm::parse_from(paths::subcommand_args());
             match submatches {
                 SubComm::Pomo{arg1, arg2, arg3} => {
                     pomodoro(arg1, arg2, arg3);
@@ -369,10 +513,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
         },
         "eadd" => {
-            let submatches = SubComm::parse();
+            let submatches = SubComm::parse_from(paths::subcommand_args());
             match submatches {
                 SubComm::Eadd{arg1, arg2, arg3, arg4} => {
-                    add_event(&mut events, arg1, arg2, arg3, arg4, next_event_id);
+                    add_event(&mut store.events, arg1, arg2, arg3, arg4, next_event_id);
                 },
                 _ => {
                     println!("invalid usage of eadd. use --help to see usage");
@@ -380,10 +524,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
         },
         "elist" => {
-            let submatches = SubComm::parse();
+            let submatches = SubComm::parse_from(paths::subcommand_args());
             match submatches {
                 SubComm::Elist{} => {
-                    daily_agenda(&mut events);
+                    daily_agenda(&mut store.events);
                 },
                 _ => {
                     println!("invalid usage of elist. use --help to see usage");
@@ -391,10 +535,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
         },
         "eids" => {
-            let submatches = SubComm::parse();
+            let submatches = SubComm::parse_from(paths::subcommand_args());
             match submatches {
                 SubComm::Eids{} => {
-                    list_event_ids(&mut events);
+                    list_event_ids(&mut store.events);
                 },
                 _ => {
                     println!("invalid usage of eids. use --help to see usage");
@@ -402,19 +546,212 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
         },
         "erem" => {
-            let submatches = SubComm::parse();
+            let submatches = SubComm::parse_from(paths::subcommand_args());
             match submatches {
                 SubComm::Erem{arg1} => {
-                    remove_event(&mut events, arg1);
+                    remove_event(&mut store.events, arg1);
                 },
                 _ => {
                     println!("invalid usage of erem. use --help to see usage");
                 }
             }
         },
+        "init" => {
+            let submatches = SubComm::parse_from(paths::subcommand_args());
+            match submatches {
+                SubComm::Init{} => {
+                    init_project()?;
+                },
+                _ => {
+                    println!("invalid usage of init. use --help to see usage");
+                }
+            }
+        },
+        "migrate" => {
+            let submatches = SubComm::parse_from(paths::subcommand_args());
+            match submatches {
+                SubComm::Migrate{dir} => {
+                    let (tasks, events, read) = store.import_legacy(&dir)?;
+                    if read.is_empty() {
+                        println!("no {} or {} in {}", store::LEGACY_TASKS_FILE, store::LEGACY_EVENTS_FILE, dir.display());
+                    }
+                    else {
+                        //the old files are only renamed once what came from them is safely saved
+                        store.save()?;
+                        for path in read {
+                            fs::rename(&path, path.with_extension("txt.migrated"))?;
+                        }
+                        let dir = fs::canonicalize(&dir).unwrap_or(dir);
+                        println!("migrated {} tasks and {} events from {}, the old files are kept with a .migrated suffix", tasks, events, dir.display());
+                    }
+                },
+                _ => {
+                    println!("invalid usage of migrate. use --help to see usage");
+                }
+            }
+        },
+        "archive" => {
+            let submatches = SubComm::parse_from(paths::subcommand_args());
+            match submatches {
+                SubComm::Archive{days} => {
+                    let archived = store.archive_tasks(clock::now() - Duration::days(days))?;
+                    println!("archived {} completed tasks", archived);
+                },
+                _ => {
+                    println!("invalid usage of archive. use --help to see usage");
+                }
+            }
+        },
+        "undo" => {
+            let submatches = SubComm::parse_from(paths::subcommand_args());
+            match submatches {
+                SubComm::Undo{} => {
+                    match journal::undo(&mut store) {
+                        Ok(Some(entry)) => println!("undid {}: {}", entry.command, journal::describe(&entry)),
+                        Ok(None) => println!("nothing to undo"),
+                        Err(e) => println!("can't undo: {}", e),
+                    }
+                },
+                _ => {
+                    println!("invalid usage of undo. use --help to see usage");
+                }
+            }
+        },
+        "redo" => {
+            let submatches = SubComm::parse_from(paths::subcommand_args());
+            match submatches {
+                SubComm::Redo{} => {
+                    match journal::redo(&mut store) {
+                        Ok(Some(entry)) => println!("redid {}: {}", entry.command, journal::describe(&entry)),
+                        Ok(None) => println!("nothing to redo"),
+                        Err(e) => println!("can't redo: {}", e),
+                    }
+                },
+                _ => {
+                    println!("invalid usage of redo. use --help to see usage");
+                }
+            }
+        },
+        "log" => {
+            let submatches = SubComm::parse_from(paths::subcommand_args());
+            match submatches {
+                SubComm::Log{number} => {
+                    show_log(&store, number)?;
+                },
+                _ => {
+                    println!("invalid usage of log. use --help to see usage");
+                }
+            }
+        },
+        "start" => {
+            let submatches = SubComm::parse_from(paths::subcommand_args());
+            match submatches {
+                SubComm::Start{arg1} => {
+                    tracking::start(&mut store.tasks, &arg1);
+                },
+                _ => {
+                    println!("invalid usage of start. use --help to see usage");
+                }
+            }
+        },
+        "stop" => {
+            let submatches = SubComm::parse_from(paths::subcommand_args());
+            match submatches {
+                SubComm::Stop{} => {
+                    tracking::stop(&mut store.tasks);
+                },
+                _ => {
+                    println!("invalid usage of stop. use --help to see usage");
+                }
+            }
+        },
+        "report" => {
+            let submatches = SubComm::parse_from(paths::subcommand_args());
+            match submatches {
+                SubComm::Report{kind: _, week, csv} => {
+                    //archived tasks keep their tracked time
+                    let mut tasks = store::load_archive(&store.dir)?;
+                    tasks.extend(store.tasks.iter().cloned());
+                    tracking::report(&tasks, week, csv);
+                },
+                _ => {
+                    println!("invalid usage of report. use --help to see usage");
+                }
+            }
+        },
+        "annotate" => {
+            let submatches = SubComm::parse_from(paths::subcommand_args());
+            match submatches {
+                SubComm::Annotate{arg1, text} => {
+                    info::annotate(&mut store.tasks, &arg1, &text.join(" "));
+                },
+                _ => {
+                    println!("invalid usage of annotate. use --help to see usage");
+                }
+            }
+        },
+        "check" => {
+            let submatches = SubComm::parse_from(paths::subcommand_args());
+            match submatches {
+                SubComm::Check{arg1, args, untick} => {
+                    if arg1 == "add" {
+                        match args.split_first() {
+                            Some((key, text)) if !text.is_empty() => checklist::add(&mut store.tasks, key, &text.join(" ")),
+                            _ => println!("invalid usage of check. use --help to see usage"),
+                        }
+                    }
+                    else if args.is_empty() {
+                        checklist::show(&store.tasks, &arg1);
+                    }
+                    else {
+                        checklist::tick(&mut store.tasks, &arg1, &args, !untick);
+                    }
+                },
+                _ => {
+                    println!("invalid usage of check. use --help to see usage");
+                }
+            }
+        },
+        "info" => {
+            let submatches = SubComm::parse_from(paths::subcommand_args());
+            match submatches {
+                SubComm::Info{arg1} => {
+                    info::show(&store.dir, &store.tasks, &arg1)?;
+                },
+                _ => {
+                    println!("invalid usage of info. use --help to see usage");
+                }
+            }
+        },
+        "graph" => {
+            let submatches = SubComm::parse_from(paths::subcommand_args());
+            match submatches {
+                SubComm::Graph{} => {
+                    print!("{}", deps::graph(&store.tasks));
+                },
+                _ => {
+                    println!("invalid usage of graph. use --help to see usage");
+                }
+            }
+        },
+        "export" => {
+            let submatches = SubComm::parse_from(paths::subcommand_args());
+            match submatches {
+                SubComm::Export{format, filter} => {
+                    match query::Query::parse(&filter) {
+                        Ok(filter) if format == "ics" => export_calendar(&store.tasks, &store.events, &filter),
+                        Ok(filter) => export_tasks(&store.tasks, &filter)?,
+                        Err(e) => println!("{}", e),
+                    }
+                },
+                _ => {
+                    println!("invalid usage of export. use --help to see usage");
+                }
+            }
+        },
         "" => {
             //if no command is given, run the vayu ui
-            vayu_ui(&mut tasks, &mut events).expect("error");
+            vayu_ui(&store.dir, &mut store.tasks, &mut store.events).expect("error");
         },
         _ => {
             println!("invalid command. use --help to see usage");
@@ -423,169 +760,488 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
 
     }
-    //write the task list to the file
-    let mut file = File::create("tasks.txt").expect("Unable to create file");
-    for task in &tasks {
-        let task_str = format!("{}%{}%{}%{}\n", task.description, task.due, task.done, task.id);
-        file.write_all(task_str.as_bytes()).expect("Unable to write data");
+    //write the task and event lists back to the stores
+    if access == Access::Write {
+        //undo and redo save and journal themselves
+        if !matches!(matches.command.as_str(), "undo" | "redo") {
+            journal::save_and_record(&mut store, &before, &matches.command)?;
+        }
     }
 
-    //write the event list to the file
-    let mut file = File::create("events.txt").expect("Unable to create file");
-    for event in events {
-        let event_str = format!("{}%{}%{}%{}%{}\n", event.description, event.start, event.end, event.repeat, event.id);
-        file.write_all(event_str.as_bytes()).expect("Unable to write data");
+    Ok(())
+}
+
+
+//creates a project local .vayu directory so this directory (and its subdirectories) get their own task list
+fn init_project() -> io::Result<()> {
+    let dir = std::env::current_dir()?.join(paths::PROJECT_DIR);
+    if dir.is_dir() {
+        println!("{} already exists", dir.display());
+    }
+    else {
+        fs::create_dir(&dir)?;
+        println!("created project task list in {}", dir.display());
     }
+    Ok(())
+}
 
+fn show_log(store: &Store, number: usize) -> io::Result<()> {
+    let entries = journal::read(&store.dir)?;
+    if entries.is_empty() {
+        println!("no operations recorded yet");
+        return Ok(());
+    }
+    println!("#    | When             | Command | Changes");
+    println!("-----|------------------|---------|--------");
+    for entry in entries.iter().rev().take(number) {
+        let mut seq = entry.seq.to_string();
+        while seq.len() < 5 {
+            seq.push(' ');
+        }
+        let mut command = entry.command.clone();
+        while command.len() < 7 {
+            command.push(' ');
+        }
+        println!("{}| {} | {} | {}", seq.green(), entry.at.format("%Y-%m-%d %H:%M"), command, journal::describe(entry));
+    }
     Ok(())
 }
 
+//the filter takes words starting with a hyphen (-tag, -@context), and once clap is filling it
+//it takes every word after too, so `vayu list +infra --sort id` would look for a "--sort" term.
+//list's own flags are moved in front of the filter. everything after -- is left where it is
+fn list_flags_first(args: Vec<String>) -> Vec<String> {
+    let mut args = args.into_iter();
+    //the program and the command
+    let mut flags: Vec<String> = args.by_ref().take(2).collect();
+    let mut filter = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--" => {
+                filter.push(arg);
+                filter.extend(args.by_ref());
+            },
+            "--sort" | "--soon" => {
+                flags.push(arg);
+                flags.extend(args.next());
+            },
+            "--done" | "--all" | "-h" | "--help" => flags.push(arg),
+            _ if arg.starts_with("--sort=") || arg.starts_with("--soon=") => flags.push(arg),
+            _ => filter.push(arg),
+        }
+    }
+    flags.extend(filter);
+    flags
+}
 
-fn list_tasks(tasks: &mut Vec<Task>) {
-    //sort the tasks by due date and store in dtasks
+fn list_tasks(tasks: &mut [Task], done: bool, all: bool, sort: &str, soon: i64, filter: &query::Query) {
+    //sort the tasks (by due date unless asked otherwise) and store in dtasks
     let dtasks = tasks;
-    dtasks.sort_by(|t1, t2| t1.due.cmp(&t2.due));
-    //display the tasks
-    println!("ID  | Due Date   | Task Description");
-    println!("----|------------|-----------------");
-    let mut i = 0;
-    for task in dtasks {
-        if !task.done {
-            //make task id a len 3 string pad with spaces
-            let mut id = task.id.to_string();
-            while id.len() < 4 {
-                id.push(' ');
-            }
-            //if due date is today use red text
-            if task.due == Local::now().format("%Y-%m-%d").to_string() {
-                println!("{}| {} | {}", id.green(), task.due.clone().red(), task.description.clone().red());
-            }
-            else if i % 2 == 0 {
-                println!("{}| {} | {}", id.green(), task.due, task.description);
-            } 
-            else {
-                println!("{}| {} | {}", id.green(), task.due, task.description);
+    let now = clock::now();
+    let today = now.format("%Y-%m-%d").to_string();
+    match sort {
+        "urgency" => urgency::sort_by_urgency(dtasks, now),
+        "priority" => dtasks.sort_by(|t1, t2| t1.priority.unwrap_or(5).cmp(&t2.priority.unwrap_or(5)).then_with(|| t1.due.cmp(&t2.due))),
+        "id" => dtasks.sort_by_key(|t| t.id),
+        "due" => dtasks.sort_by(|t1, t2| t1.due.cmp(&t2.due).then_with(|| t1.due_at.cmp(&t2.due_at))),
+        //tasks without a scheduled date go last
+        "scheduled" => dtasks.sort_by(|t1, t2| {
+            (t1.scheduled.is_none(), &t1.scheduled, &t1.due).cmp(&(t2.scheduled.is_none(), &t2.scheduled, &t2.due))
+        }),
+        //an attribute from config.toml. tasks due on the same day are ordered by their due time
+        name => {
+            if let Some(uda) = config::uda(name) {
+                dtasks.sort_by(|t1, t2| config::compare(uda, t1.extra.get(name), t2.extra.get(name)).then_with(|| t1.due.cmp(&t2.due)));
             }
-            i += 1;
+        },
+    }
+    //completed tasks get an extra column with the date they were finished
+    let history = done || all;
+    //urgency gets a column when it's what the list is sorted by
+    let show_urgency = sort == "urgency";
+    let shown: Vec<usize> = (0..dtasks.len()).filter(|&i| {
+        let task = &dtasks[i];
+        //a query that asks for a status overrides the default of showing pending tasks
+        let wrong_status = !filter.mentions_status() && ((task.done && !done && !all) || (!task.done && done));
+        //waiting tasks stay hidden until their wait date unless asked for
+        let hidden = task.is_waiting(&today) && !all && !filter.mentions_waiting();
+        !wrong_status && !hidden && filter.matches(dtasks, task)
+    }).collect();
+    //scheduled dates get a column when any task shown has one
+    let show_scheduled = shown.iter().any(|&i| dtasks[i].scheduled.is_some());
+    //and the workflow state when a pending task has moved on from the first one
+    let state_width = shown.iter()
+        .any(|&i| !dtasks[i].done && workflow::state(&dtasks[i]) != workflow::first_state())
+        .then(|| config::states().iter().map(|s| s.chars().count()).max().unwrap_or(0).max(5));
+    //so does the attribute the list is sorted by
+    let uda_column = config::uda(sort).map(|_| {
+        let width = shown.iter().map(|&i| config::show(dtasks[i].extra.get(sort)).chars().count()).max().unwrap_or(0).max(sort.len());
+        (sort, width)
+    });
+    //the due column widens to fit a time when any task has one
+    let due_width = if shown.iter().any(|&i| dtasks[i].due_at.is_some()) { 16 } else { 10 };
+    //display the tasks
+    let mut header = "ID  | P | ".to_string();
+    let mut divider = "----|---|-".to_string();
+    if show_urgency {
+        header.push_str("Urg  | ");
+        divider.push_str("-----|-");
+    }
+    if show_scheduled {
+        header.push_str("Scheduled  | ");
+        divider.push_str("-----------|-");
+    }
+    if let Some(width) = state_width {
+        header.push_str(&format!("{:<width$} | ", "State", width = width));
+        divider.push_str(&format!("{}|-", "-".repeat(width + 1)));
+    }
+    if let Some((name, width)) = uda_column {
+        header.push_str(&format!("{:<width$} | ", name, width = width));
+        divider.push_str(&format!("{}|-", "-".repeat(width + 1)));
+    }
+    header.push_str(&format!("{:<width$} | ", "Due Date", width = due_width));
+    divider.push_str(&format!("{}|-", "-".repeat(due_width + 1)));
+    if history {
+        header.push_str("Completed  | ");
+        divider.push_str("-----------|-");
+    }
+    header.push_str("Task Description");
+    divider.push_str("----------------");
+    println!("{}", header);
+    println!("{}", divider);
+    //subtasks are indented under their parent
+    for (i, depth) in tree::order(dtasks, &shown) {
+        let task = &dtasks[i];
+        //make task id a len 3 string pad with spaces
+        let mut id = task.id.to_string();
+        while id.len() < 4 {
+            id.push(' ');
+        }
+        let priority = task.priority.map(|p| p.to_string()).unwrap_or(" ".to_string());
+        //overdue tasks, tasks due today and ones with a due time coming up are red, completed tasks are dimmed
+        let mut description = format!("{}{}", tree::indent(depth), task.labelled_description());
+        if let Some((finished, total)) = tree::progress(dtasks, task.id) {
+            description.push_str(&format!(" [{}/{}]", finished, total));
+        }
+        if let Some((ticked, total)) = checklist::progress(task) {
+            description.push_str(&format!(" ({}/{} checked)", ticked, total));
+        }
+        if task.is_active() {
+            description.push_str(&format!(" (started, {} tracked)", tracking::hours_minutes(tracking::tracked(task, now))));
         }
+        let due = format!("{:<width$}", task.due_text(), width = due_width);
+        let (due, description) = if task.done {
+            (due.dim(), description.dim())
+        }
+        else if due_soon(task, now, soon) {
+            (due.red(), description.red())
+        }
+        else {
+            (due.stylize(), description.stylize())
+        };
+        let mut line = format!("{}| {} | ", id.green(), priority);
+        if show_urgency {
+            line.push_str(&format!("{:<4.1} | ", urgency::urgency(dtasks, task, now)));
+        }
+        if show_scheduled {
+            line.push_str(&format!("{:<10} | ", task.scheduled.as_deref().unwrap_or("")));
+        }
+        if let Some(width) = state_width {
+            line.push_str(&format!("{:<width$} | ", workflow::state(task), width = width));
+        }
+        if let Some((name, width)) = uda_column {
+            line.push_str(&format!("{:<width$} | ", config::show(task.extra.get(name)), width = width));
+        }
+        line.push_str(&format!("{} | ", due));
+        if history {
+            let completed = match task.completed_at {
+                Some(completed_at) => completed_at.format("%Y-%m-%d").to_string(),
+                None => "          ".to_string(),
+            };
+            line.push_str(&format!("{} | ", completed));
+        }
+        println!("{}{}", line, description);
     }
 
 }
 
-fn add_task(tasks: &mut Vec<Task>, next_id: i32, arg1: String) {
-    //parse the task description and due date from the arg1 string
-    let arg1_vec : Vec<&str> = arg1.split(":").collect();
-    if arg1_vec.len() != 2 {
-        println!("invalid usage of add. use --help to see usage");
-        return;
-    }
-    let mut task_desc = arg1_vec[0].to_string();
-    task_desc.truncate(task_desc.len() - 3);
-    let mut due_date = arg1_vec[1].to_string();
-    //remove \n from the end of due_date
-    due_date = due_date.replace("\n", "");
+fn add_task(tasks: &mut Vec<Task>, next_id: i32, arg1: String, parent: Option<String>) {
+    //parse the task description and the field:value words (due:today, priority:H) from the arg1 string
+    let words: Vec<String> = arg1.split_whitespace().map(String::from).collect();
+    let (fields, description) = edit::split_fields(&words, false);
+    let due_date = match fields.iter().find(|(key, _)| key == "due") {
+        Some((_, due)) => due.clone(),
+        None => {
+            println!("invalid usage of add. use --help to see usage");
+            return;
+        }
+    };
     println!("due date: {}", due_date);
-    //if the due date is "today" or "tomorrow" or "yesterday" or a day of the week, set the due date to the appropriate date
-    //we use DateTime<Local> to get the current date and time
-    let now = Local::now();
-    //calculate number of days from 
-    if due_date == "today" {
-        due_date = now.format("%Y-%m-%d").to_string();
-    } 
-    else if due_date == "tomorrow" {
-        due_date = (now + Duration::days(1)).format("%Y-%m-%d").to_string();
-    } 
-    else if due_date == "yesterday" {
-        due_date = (now - Duration::days(1)).format("%Y-%m-%d").to_string();
-    }
-    else if due_date == "sunday" || due_date == "monday" || due_date == "tuesday" || due_date == "wednesday" || due_date == "thursday" || due_date == "friday" || due_date == "saturday"{
-        let mut duecp = due_date.clone();
-        let capsdate = duecp.remove(0).to_uppercase().to_string() + &duecp;
-        let mut day = now;
-        while day.format("%A").to_string() != capsdate{
-            day = day + Duration::days(1);
+    //create a new task and add it to the task list. due dates like "today" or "friday" are turned into YYYY-MM-DD
+    let mut task = Task::new(next_id, String::new(), String::new());
+    config::apply_defaults(&mut task);
+    if let Some(parent) = parent {
+        match store::find_task(tasks, &parent) {
+            Ok(index) => task.parent = Some(tasks[index].id),
+            Err(e) => {
+                println!("{}", e);
+                return;
+            }
         }
-        if now.format("%A").to_string() == capsdate{
-            day = day + Duration::days(7);
+    }
+    for (key, value) in &fields {
+        if let Err(e) = edit::set_field(&mut task, key, value) {
+            println!("{}", e);
+            return;
         }
-        due_date = day.format("%Y-%m-%d").to_string();
     }
-    else{
-        println!("invalid due date. use YYYY-MM-DD or today, tomorrow, yesterday, or a day of the week");
+    if let Err(e) = edit::set_field(&mut task, "description", &description) {
+        println!("{}", e);
+        return;
     }
-
-
-
-    //create a new task and add it to the task list
-    let task = Task {
-        description: task_desc,
-        due: due_date,
-        done: false,
-        id: next_id,
-    };
+    if let Err(e) = deps::check(tasks, &task) {
+        println!("{}", e);
+        return;
+    }
+    println!("task added with id {} ({})", next_id, store::short_uuid(&task.uuid));
     tasks.push(task);
-    println!("task added with id {}", next_id)
 }
 
-use chrono::format::strftime::StrftimeItems;
-
-fn add_auto(tasks: &mut Vec<Task>, next_id: i32, arg1: String) {   
-    let fetchtask = DateParser::parse(&arg1);
-    let fmt = StrftimeItems::new("%Y-%m-%d");
+fn add_auto(dir: &std::path::Path, arg1: String) -> io::Result<()> {
+    //field:value words like priority:H are taken out before the date is looked for
+    let words: Vec<String> = arg1.split_whitespace().map(String::from).collect();
+    let (fields, arg1) = edit::split_fields(&words, false);
+    let fetchtask = dates::find_when(&arg1, clock::now().naive_local());
     //parse the option
     match fetchtask {
         //some or none
-        Some(date) => {
+        Some((when, description)) => {
             //ask user to confirm
-            let dt = date.format_with_items(fmt.clone()).to_string();
-            println!("auto generated task: {} due on {}", arg1, dt);
+            println!("auto generated task: {} due on {}", description, when);
             println!("confirm? (y/n)");
             let mut confirm = String::new();
             io::stdin().read_line(&mut confirm).expect("error");
-            if confirm.trim() == "y" {
-                let task = Task {
-                    description: arg1,
-                    due: dt,
-                    done: false,
-                    id: next_id,
-                };
-                tasks.push(task);
-                println!("task added with id {}", next_id);
-            }
-            else {
+            if confirm.trim() != "y" {
                 println!("task not added");
+                return Ok(());
             }
+            journal::update(dir, "auto", |store| {
+                let next_id = store.next_task_id();
+                let mut task = Task::new(next_id, String::new(), String::new());
+                config::apply_defaults(&mut task);
+                let due = [("due".to_string(), when.to_string()), ("description".to_string(), description)];
+                for (key, value) in due.iter().chain(&fields) {
+                    if let Err(e) = edit::set_field(&mut task, key, value) {
+                        println!("{}. task not added", e);
+                        return;
+                    }
+                }
+                if let Err(e) = deps::check(&store.tasks, &task) {
+                    println!("{}. task not added", e);
+                    return;
+                }
+                println!("task added with id {} ({})", next_id, store::short_uuid(&task.uuid));
+                store.tasks.push(task);
+            })?;
         },
         None => {
-            println!("invalid usage of auto. use --help to see usage");
+            println!("no date found in \"{}\". use --help to see usage", arg1);
         }  
     }
+    Ok(())
+}
 
+fn complete_tasks(dir: &std::path::Path, tasks: &[Task], arg1: String) -> io::Result<()> {
+    //find the tasks by id, uuid prefix or query and mark them as done. completed tasks stay in the list as history
+    let selected = match query::select(tasks, &arg1) {
+        Ok(selected) => selected,
+        Err(e) => {
+            println!("{}", e);
+            return Ok(());
+        }
+    };
+    if !query::is_key(&arg1) {
+        let pending: Vec<usize> = selected.into_iter().filter(|&i| !tasks[i].done).collect();
+        if pending.is_empty() {
+            println!("all matching tasks are already done");
+            return Ok(());
+        }
+        if !confirm_bulk(tasks, &pending, "complete") {
+            return Ok(());
+        }
+        let ids = pending.iter().map(|&i| tasks[i].id).collect();
+        return complete_with_subtasks(dir, tasks, ids, "done");
+    }
+    let task = &tasks[selected[0]];
+    if task.done {
+        println!("task {} is already done", task.id);
+        return Ok(());
+    }
+    complete_with_subtasks(dir, tasks, vec![task.id], "done")
+}
 
+//marks the tasks done, first asking whether their open subtasks should be completed with them.
+//the questions are asked about `tasks`, read without the lock, and the answer applied to the store on disk
+fn complete_with_subtasks(dir: &std::path::Path, tasks: &[Task], mut ids: Vec<i32>, command: &str) -> io::Result<()> {
+    let mut open: Vec<i32> = ids.iter().flat_map(|&id| tree::open_descendants(tasks, id)).collect();
+    open.retain(|id| !ids.contains(id));
+    open.sort();
+    open.dedup();
+    if !open.is_empty() {
+        println!("there are {} open subtasks:", open.len());
+        for task in tasks.iter().filter(|t| open.contains(&t.id)) {
+            println!("{} {}", task.id.to_string().green(), task.labelled_description());
+        }
+        println!("complete them too? (y = complete them, n = leave them open, c = cancel)");
+        let mut confirm = String::new();
+        io::stdin().read_line(&mut confirm).expect("error");
+        match confirm.trim() {
+            "y" => ids.extend(open),
+            "n" => {},
+            _ => {
+                println!("no tasks changed");
+                return Ok(());
+            }
+        }
+    }
+    let chosen: Vec<usize> = (0..tasks.len()).filter(|&i| ids.contains(&tasks[i].id)).collect();
+    journal::update(dir, command, |store| {
+        let ids: Vec<i32> = reselect(tasks, &chosen, &store.tasks).into_iter()
+            .filter(|&i| !store.tasks[i].done)
+            .map(|i| store.tasks[i].id)
+            .collect();
+        let next_id = store.next_task_id();
+        for message in workflow::complete(&mut store.tasks, next_id, &ids, clock::now()) {
+            println!("{}", message);
+        }
+    })
 }
 
-fn remove_task(tasks: &mut Vec<Task>, arg1: String) {
-    //parse the task id from the arg1 string
-    let task_id = arg1.parse::<i32>().unwrap();
-    //find the task with the given id and remove it from the task list
-    let mut index = 0;
-    for task in &mut tasks.iter() {
-        if task.id == task_id {
-            tasks.swap_remove(index);
-            println!("task {} done", task_id);
-            return;
+//moves tasks to another workflow state. moving to the last state completes them like `vayu done`
+fn move_tasks(dir: &std::path::Path, tasks: &[Task], arg1: String, state: &str) -> io::Result<()> {
+    let state = match workflow::parse(state) {
+        Ok(state) => state,
+        Err(e) => {
+            println!("{}", e);
+            return Ok(());
         }
-        index += 1;
+    };
+    let selected = match query::select(tasks, &arg1) {
+        Ok(selected) => selected,
+        Err(e) => {
+            println!("{}", e);
+            return Ok(());
+        }
+    };
+    let (already, selected): (Vec<usize>, Vec<usize>) = selected.into_iter().partition(|&i| workflow::state(&tasks[i]) == state);
+    for &i in &already {
+        println!("task {} is already in {}", tasks[i].id, state);
+    }
+    if selected.is_empty() || !confirm_bulk(tasks, &selected, &format!("move to {}", state)) {
+        return Ok(());
+    }
+    if state == workflow::done_state() {
+        let ids: Vec<i32> = selected.iter().map(|&i| tasks[i].id).collect();
+        return complete_with_subtasks(dir, tasks, ids, "move");
     }
-    println!("task with id {} not found", task_id);
+    journal::update(dir, "move", |store| {
+        let now = clock::now();
+        for i in reselect(tasks, &selected, &store.tasks) {
+            let reopened = store.tasks[i].done;
+            workflow::enter(&mut store.tasks[i], state, now);
+            if reopened {
+                println!("task {} reopened in {}", store.tasks[i].id, state);
+            }
+            else {
+                println!("task {} moved to {}", store.tasks[i].id, state);
+            }
+        }
+    })
+}
+
+//the tasks picked out of `read` (indexes into it) found again by uuid in `current`, the store as it is now.
+//a task another command removed in between is left out
+fn reselect(read: &[Task], selected: &[usize], current: &[Task]) -> Vec<usize> {
+    selected.iter().filter_map(|&i| {
+        let found = current.iter().position(|t| t.uuid == read[i].uuid);
+        if found.is_none() {
+            println!("task {} was removed by another command", read[i].id);
+        }
+        found
+    }).collect()
+}
+
+//lists the tasks a query picked out and asks before changing more than one
+fn confirm_bulk(tasks: &[Task], selected: &[usize], verb: &str) -> bool {
+    if selected.len() == 1 {
+        return true;
+    }
+    for &i in selected {
+        println!("{} {}", tasks[i].id.to_string().green(), tasks[i].labelled_description());
+    }
+    println!("{} these {} tasks? (y/n)", verb, selected.len());
+    let mut confirm = String::new();
+    io::stdin().read_line(&mut confirm).expect("error");
+    if confirm.trim() != "y" {
+        println!("no tasks changed");
+        return false;
+    }
+    true
+}
+
+fn modify_tasks(dir: &std::path::Path, tasks: &[Task], arg1: String, args: Vec<String>) -> io::Result<()> {
+    let selected = match query::select(tasks, &arg1) {
+        Ok(selected) => selected,
+        Err(e) => {
+            println!("{}", e);
+            return Ok(());
+        }
+    };
+    if !confirm_bulk(tasks, &selected, "modify") {
+        return Ok(());
+    }
+    journal::update(dir, "modify", |store| {
+        let selected = reselect(tasks, &selected, &store.tasks);
+        //work on a copy so a bad value leaves every task untouched
+        let mut changed = store.tasks.clone();
+        for &i in &selected {
+            if let Err(e) = edit::modify(&mut changed[i], &args) {
+                println!("{}. no tasks changed", e);
+                return;
+            }
+        }
+        //dependencies are checked once every change is in, so a cycle between the modified tasks is caught
+        for &i in &selected {
+            if let Err(e) = deps::check(&changed, &changed[i]) {
+                println!("{}. no tasks changed", e);
+                return;
+            }
+        }
+        for i in selected {
+            println!("task {} updated", changed[i].id);
+            store.tasks[i] = changed[i].clone();
+        }
+    })
 }
 
+//prints the matching tasks, pending and completed, as a json array
+fn export_tasks(tasks: &[Task], filter: &query::Query) -> io::Result<()> {
+    let matching: Vec<&Task> = tasks.iter().filter(|task| filter.matches(tasks, task)).collect();
+    println!("{}", serde_json::to_string_pretty(&matching)?);
+    Ok(())
+}
+
+//prints the events and the matching tasks as an iCalendar file, ex: vayu export ics > vayu.ics
+fn export_calendar(tasks: &[Task], events: &[Event1], filter: &query::Query) {
+    let matching: Vec<Task> = tasks.iter().filter(|task| filter.matches(tasks, task)).cloned().collect();
+    print!("{}", ics::calendar(&matching, events, clock::now()));
+}
 
 fn pomodoro(arg1: String, arg2: String, arg3: String){
     //we will use the chrono crate to get the current time and to calculate the time remaining
     //we will use indicatif to display a progress bar
     //if any of the arguments are empty, throw error
-    if arg1 == "" || arg2 == "" || arg3 == "" {
+    if arg1.is_empty() || arg2.is_empty() || arg3.is_empty() {
         println!("invalid usage of pomo. use --help to see usage");
         return;
     }
@@ -596,7 +1252,7 @@ fn pomodoro(arg1: String, arg2: String, arg3: String){
     
     for i in 0..iterations {
         //reupdate end times and start times
-        let mut now = Local::now();
+        let mut now = clock::now();
         let work_end = now + Duration::minutes(work_time.into());
         //work session
         let mut pb = ProgressBar::new(work_time as u64);
@@ -607,7 +1263,7 @@ fn pomodoro(arg1: String, arg2: String, arg3: String){
         //figure out number of seconds in one increment (divide seconds by number of increments (40))
         let mut increment = (work_time * 60) as f64 / 40.0;
         for j in 0..(work_time * 60) {
-            let remaining = work_end - Local::now();
+            let remaining = work_end - clock::now();
             //minutes and seconds
             if j % increment as i32 == 0 {
                 pb.inc(1);
@@ -619,7 +1275,7 @@ fn pomodoro(arg1: String, arg2: String, arg3: String){
         pb.set_message("work session complete".to_string());
         pb.finish_and_clear();
         //break session
-        now = Local::now();
+        now = clock::now();
         let break_end = now + Duration::minutes(break_time.into());
 
         pb = ProgressBar::new(break_time as u64);
@@ -630,7 +1286,7 @@ fn pomodoro(arg1: String, arg2: String, arg3: String){
         //figure out number of seconds in one increment (divide seconds by number of increments (40))
         increment = (break_time * 60) as f64 / 40.0;
         for j in 0..(break_time * 60) {
-            let remaining = break_end - Local::now();
+            let remaining = break_end - clock::now();
             //minutes and seconds
             if j % increment as i32 == 0 {
                 pb.inc(1);
@@ -647,217 +1303,172 @@ fn pomodoro(arg1: String, arg2: String, arg3: String){
 
 }
 
-fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>>
-    where P: AsRef<Path>, {
-        let file = File::open(filename)?;
-        Ok(io::BufReader::new(file).lines())
-}
-
 fn add_event(events: &mut Vec<Event1>, arg1: String, arg2: String, arg3: String, arg4: String, next_id: i32){
     //if any arguments are empty, throw error
-    if arg1 == "" || arg2 == "" || arg3 == "" {
+    if arg1.is_empty() || arg2.is_empty() || arg3.is_empty() {
         println!("invalid usage of eadd. use --help to see usage");
         return;
     }
     let event_desc = arg1;
-    let mut start_time = arg2;
-    let mut end_time = arg3;
     let mut repeat = arg4;
     //remove \n from the end of due_date
-    start_time = start_time.replace("\n", "");
-    end_time = end_time.replace("\n", "");
     repeat = repeat.replace("\n", "");
-    println!("start time: {}", start_time);
-    println!("end time: {}", end_time);
-    println!("repeat: {}", repeat);
-    //start and end time should be in the format H:MMam || H:MMpm etc.
+    //times can be typed as 5:30pm, 5pm or 17:30
     //repeat should be in the format day1,day2,day3,day4,day5,day6,day7 where dayi is a day of the week
     //create a new event and add it to the event list
     //check to make sure the above criteria are met else through error
-    if start_time.find(":").is_none() || (start_time.find("am").is_none() && start_time.find("pm").is_none()) {
-        println!("invalid start time format. use H:MMam or H:MMpm");
-        return;
-    }
-    if end_time.find(":").is_none() || (end_time.find("am").is_none() && end_time.find("pm").is_none()) {
-        println!("invalid end time format. use H:MMam or H:MMpm");
+    let (start_time, end_time) = match (dates::parse_time(&arg2), dates::parse_time(&arg3)) {
+        (Ok(start), Ok(end)) => (start, end),
+        (Err(e), _) | (_, Err(e)) => {
+            println!("{}", e);
+            return;
+        }
+    };
+    if end_time <= start_time {
+        println!("the event has to end after it starts ({} - {})", dates::format_time(start_time), dates::format_time(end_time));
         return;
     }
-    let repeatclone = repeat.clone();
-    let vecrepeat : Vec<&str> = repeatclone.split(",").collect();
-    //make sure all vect elements are days of the week
-    if repeat == "everyday" {
-        repeat = "monday,tuesday,wednesday,thursday,friday,saturday,sunday".to_string();
-    }
-    if repeat == "weekday" {
-        repeat = "monday,tuesday,wednesday,thursday,friday".to_string();
-    }
-    if repeat == "weekend" {
-        repeat = "saturday,sunday".to_string();
-    }
-    if repeat != "" {
-        for day in vecrepeat {
-            if day != "sunday" && day != "monday" && day != "tuesday" && day != "wednesday" && day != "thursday" && day != "friday" && day != "saturday" && (repeat.matches("-").count() != 2) && day != "everyday" && day != "weekday" && day != "weekend" {
-                println!("invalid repeat format. use subset of [monday,tuesday,wednesday,thursday,friday,saturday,sunday] separated by commas, YYYY-MM-DD, or one of [everyday,weekday,weekend]");
+    println!("start time: {}", dates::format_time(start_time));
+    println!("end time: {}", dates::format_time(end_time));
+    println!("repeat: {}", repeat);
+    let days: Vec<&str> = dates::WEEKDAYS.iter().map(|(name, _)| *name).collect();
+    repeat = match repeat.trim().to_lowercase().as_str() {
+        //if repeat is empty, set repeat to date in YYYY-MM-DD format
+        "" => clock::now().format("%Y-%m-%d").to_string(),
+        "everyday" => days.join(","),
+        "weekday" => days[..5].join(","),
+        "weekend" => days[5..].join(","),
+        list if list.split(',').all(|day| days.contains(&day)) => list.to_string(),
+        //a one off event on a date, typed like a due date (2024-04-25, tomorrow, next friday)
+        date => match dates::parse_date(date, clock::now().date_naive()) {
+            Ok(date) => date.format("%Y-%m-%d").to_string(),
+            Err(_) => {
+                println!("invalid repeat format. use subset of [monday,tuesday,wednesday,thursday,friday,saturday,sunday] separated by commas, a date (YYYY-MM-DD, tomorrow, next friday), or one of [everyday,weekday,weekend]");
                 return;
             }
-        }
-    }   
-    else {
-        //if repeat is empty, set repeat to date in YYYY-MM-DD format
-        repeat = Local::now().format("%Y-%m-%d").to_string();
-    }
+        },
+    };
     //if all criteria are met, add the event to the event list
     let event = Event1 {
         description: event_desc,
         start: start_time,
         end: end_time,
-        repeat: repeat,
+        repeat,
         id: next_id,
+        uuid: Uuid::new_v4(),
+        extra: Map::new(),
     };
+    println!("event added with id {} ({})", next_id, store::short_uuid(&event.uuid));
     events.push(event);
-    println!("event added with id {}", next_id);
 }
 
-fn daily_agenda(events: &mut Vec<Event1>) {
-    //get the current date
-    let now = Local::now();
-    let today_date = now.format("%Y-%m-%d").to_string();
-    //get the day of the week as a string (monday, tuesday, etc.)
-    //let today_day = now.format("%A").to_string();
-    let today_day = "wednesday";
-    //lowercase the day of the week
-    let today_day = today_day.to_lowercase();
-    let mut todays_events : Vec<Event1> = Vec::new();
+//events that happen on a day: one off events on that date and repeating ones on that day of the week
+fn events_on(events: &[Event1], day: NaiveDate) -> Vec<Event1> {
+    let day_date = day.format("%Y-%m-%d").to_string();
+    let day_day = day.format("%A").to_string().to_lowercase();
+    events.iter().filter(|event| event.repeat == day_date || event.repeat.contains(&day_day)).cloned().collect()
+}
+
+//yesterday and the six days after it, for the dashboard's weekly calendar
+fn calendar_days(today: NaiveDate) -> Vec<NaiveDate> {
+    (-1..6).map(|offset| today + Duration::days(offset)).collect()
+}
+
+//due dates in red: overdue, due today, or a due time within `soon` hours
+fn due_soon(task: &Task, now: DateTime<Local>, soon: i64) -> bool {
+    let today = now.format("%Y-%m-%d").to_string();
+    !task.done && !task.due.is_empty()
+        && (task.due <= today || task.due_at.is_some_and(|due_at| due_at <= now + Duration::hours(soon)))
+}
+
+fn daily_agenda(events: &mut [Event1]) {
     //get all events that repeat on today's date or today's day of the week
-    for event in events {
-        if event.repeat == today_date || event.repeat.contains(&today_day) {
-            let eventc = Event1 {
-                description: event.description.clone(),
-                start: event.start.clone(),
-                end: event.end.clone(),
-                repeat: event.repeat.clone(),
-                id: event.id,
-            };
-            todays_events.push(eventc);
-        }
-    }
-    todays_events.sort_by(|e1, e2| {
-        let e1_vec : Vec<&str> = e1.start.split(":").collect();
-        let e2_vec : Vec<&str> = e2.start.split(":").collect();
-        let mut e1_hour = e1_vec[0].to_string().parse::<i32>().unwrap();
-        let mut e2_hour = e2_vec[0].to_string().parse::<i32>().unwrap();
-        //last two characters of e1_vec[1] are am or pm
-        let e1_ampm = &e1_vec[1][e1_vec[1].len()-2..];
-        let e2_ampm = &e2_vec[1][e2_vec[1].len()-2..];
-        //remove am or pm from e1_vec[1] and parse to int
-        let e1_min = e1_vec[1][0..e1_vec[1].len()-2].to_string().parse::<i32>().unwrap();
-        let e2_min = e2_vec[1][0..e2_vec[1].len()-2].to_string().parse::<i32>().unwrap();
-        if e1_hour == 12 && e1_ampm == "am" {
-            e1_hour = 0;
-        }
-        if e2_hour == 12 && e2_ampm == "am" {
-            e2_hour = 0;
-        }
-        if e1_hour == 12 && e1_ampm == "pm" {
-            e1_hour = 0;
-        }
-        if e2_hour == 12 && e2_ampm == "pm" {
-            e2_hour = 0;
-        }
-        if e1_ampm == "am" && e2_ampm == "pm" {
-            return std::cmp::Ordering::Less;
-        }
-        else if e1_ampm == "pm" && e2_ampm == "am" {
-            return std::cmp::Ordering::Greater;
-        }
-        else if (e1_ampm == "am" && e2_ampm == "am") || (e1_ampm == "pm" && e2_ampm == "pm") {
-            if e1_hour < e2_hour {
-                return std::cmp::Ordering::Less;
-            }
-            else if e1_hour > e2_hour {
-                return std::cmp::Ordering::Greater;
-            }
-            else {
-                if e1_min < e2_min {
-                    return std::cmp::Ordering::Less;
-                }
-                else if e1_min > e2_min {
-                    return std::cmp::Ordering::Greater;
-                }
-                else {
-                    return std::cmp::Ordering::Equal;
-                }
-            }
-        }
-        else {
-            return std::cmp::Ordering::Equal;
-        }
-    });
+    let mut todays_events = events_on(events, clock::now().date_naive());
+    store::sort_events(&mut todays_events);
     //display the events
     println!("Today's Agenda");
     println!("---------------------------------");
-    if todays_events.len() == 0 {
+    if todays_events.is_empty() {
         println!("No events today.");
     }
     for event in todays_events {
         //pad the start and end time with spaces to be len 7
-        let mut start_time = event.start;
-        while start_time.len() < 7 {
-            start_time.push(' ');
-        }
-        let mut end_time = event.end;
-        while end_time.len() < 7 {
-            end_time.push(' ');
-        }
-        println!("{} - {} - {}", start_time, end_time, event.description);
+        let (start_time, end_time) = event.times();
+        println!("{:<7} - {:<7} - {}", start_time, end_time, event.description);
     }
     
 }
 
 fn list_event_ids(events: &mut Vec<Event1>) {
     for event in events {
-        println!("{} - {}", event.description, event.id);
+        println!("{} - {} ({})", event.description, event.id, store::short_uuid(&event.uuid));
     }
 
 }
 
 fn remove_event(events: &mut Vec<Event1>, arg1: String) {
-    //parse the event id from the arg1 string
-    let event_id = arg1.parse::<i32>().unwrap();
-    //find the event with the given id and remove it from the event list
-    let mut index = 0;
-    for event in &mut events.iter() {
-        if event.id == event_id {
-            events.swap_remove(index);
-            println!("event {} done", event_id);
-            return;
+    //find the event by id or uuid prefix and remove it from the event list
+    match store::find_event(events, &arg1) {
+        Ok(index) => {
+            let event = events.swap_remove(index);
+            println!("event {} done", event.id);
+        },
+        Err(e) => {
+            println!("{}", e);
         }
-        index += 1;
     }
-    println!("event with id {} not found", event_id);
 }
 
-fn vayu_ui(tasks: &mut Vec<Task>, events: &mut Vec<Event1>) -> io::Result<()> {
+fn vayu_ui(dir: &std::path::Path, tasks: &mut Vec<Task>, events: &mut [Event1]) -> io::Result<()> {
     //ratatui ui with task list, calendar, and quote of the day
     //layout
     //                      *vayu*                              
     //                  quote of the day                        
     //      task list                          weekly calendar
-    let task_clone : &mut Vec<Task> = tasks;
-    let event_clone : &mut Vec<Event1> = events;
+    //'b' switches to the kanban board and back
+    let event_clone : &mut [Event1] = events;
     enable_raw_mode()?;
     stdout().execute(EnterAlternateScreen)?;
     let mut terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
 
+    let mut board: Option<board::Board> = None;
     let mut should_quit = false;
     while !should_quit {
-        terminal.draw(|f| ui(f, task_clone, event_clone))?;
+        terminal.draw(|f| match &board {
+            Some(board) => board_ui(f, tasks, board),
+            None => ui(f, tasks, event_clone),
+        })?;
         if event::poll(std::time::Duration::from_millis(50))? {
             if let UIEvent::Key(key) = event::read()? {
+                if key.kind != event::KeyEventKind::Press {
+                    continue;
+                }
                 //if key is q, quit
-                if key.kind == event::KeyEventKind::Press && key.code == KeyCode::Char('q') {
+                if key.code == KeyCode::Char('q') {
                     should_quit = true;
                 }
+                else if key.code == KeyCode::Char('b') {
+                    board = match board {
+                        Some(_) => None,
+                        None => Some(board::Board::default()),
+                    };
+                }
+                else if let Some(board) = board.as_mut() {
+                    //arrows or h/j/k/l pick a task, < and > (or H and L) move it to the previous or next state
+                    let columns = board::columns(tasks, clock::now());
+                    let moved = match key.code {
+                        KeyCode::Left | KeyCode::Char('h') => { board.left(&columns); Ok(()) },
+                        KeyCode::Right | KeyCode::Char('l') => { board.right(&columns); Ok(()) },
+                        KeyCode::Up | KeyCode::Char('k') => { board.up(); Ok(()) },
+                        KeyCode::Down | KeyCode::Char('j') => { board.down(&columns); Ok(()) },
+                        KeyCode::Char('<') | KeyCode::Char('H') => board::move_selected(dir, tasks, board, -1),
+                        KeyCode::Char('>') | KeyCode::Char('L') => board::move_selected(dir, tasks, board, 1),
+                        _ => Ok(()),
+                    };
+                    if let Err(e) = moved {
+                        board.message = format!("couldn't save: {}", e);
+                    }
+                }
             }
         }
     }
@@ -867,7 +1478,16 @@ fn vayu_ui(tasks: &mut Vec<Task>, events: &mut Vec<Event1>) -> io::Result<()> {
     Ok(())
 }
 
-fn ui(frame: &mut Frame, tasks: &mut Vec<Task>, events: &mut Vec<Event1>) {
+fn board_ui(frame: &mut Frame, tasks: &[Task], board: &board::Board) {
+    let layout = Layout::new(Direction::Vertical, [Constraint::Length(2), Constraint::Min(0)]).split(frame.size());
+    frame.render_widget(
+        Block::new().title("vayu board - arrows to pick a task, '<' '>' to move it, 'b' for the dashboard, 'q' to quit").title_alignment(Alignment::Center).style(Style::default().fg(Color::Blue).bg(Color::Black)),
+        layout[0],
+    );
+    board::render(frame, layout[1], tasks, board);
+}
+
+fn ui(frame: &mut Frame, tasks: &mut [Task], events: &mut [Event1]) {
     //main window
     let main_layout = Layout::new(
         Direction::Vertical,
@@ -894,11 +1514,10 @@ fn ui(frame: &mut Frame, tasks: &mut Vec<Task>, events: &mut Vec<Event1>) {
 
     //one box for each day of the week starting with today as the second box
     //get the current date
-    let now = Local::now();
+    let now = clock::now();
     let today_date = now.format("%Y-%m-%d").to_string();
     //render a box with each date and day of the week starting at yesterday
-    let mut day = now - Duration::days(1);
-    for i in 0..7 {
+    for (i, day) in calendar_days(now.date_naive()).into_iter().enumerate() {
         let day_date = day.format("%Y-%m-%d").to_string();
         let day_day = day.format("%A").to_string();
         let day_day = day_day.to_lowercase();
@@ -906,78 +1525,15 @@ fn ui(frame: &mut Frame, tasks: &mut Vec<Task>, events: &mut Vec<Event1>) {
         let mut day_str = day_date.clone();
         let mut cat_day = day_day.clone();
         cat_day.truncate(3);
-        day_str.push_str(" ");
+        day_str.push(' ');
         day_str.push_str(&cat_day);
         let day_box = Block::default().title(day_str.clone());
         //rendering the calendar
-        let mut todays_events : Vec<Event1> = Vec::new();
-        for event in &mut *events {
-            if event.repeat == day_date || event.repeat.contains(&day_day) {
-                let eventc = Event1 {
-                    description: event.description.clone(),
-                    start: event.start.clone(),
-                    end: event.end.clone(),
-                    repeat: event.repeat.clone(),
-                    id: event.id,
-                };
-                todays_events.push(eventc);
-            }
-        }
-        todays_events.sort_by(|e1, e2| {
-            let e1_vec : Vec<&str> = e1.start.split(":").collect();
-            let e2_vec : Vec<&str> = e2.start.split(":").collect();
-            let mut e1_hour = e1_vec[0].to_string().parse::<i32>().unwrap();
-            let mut e2_hour = e2_vec[0].to_string().parse::<i32>().unwrap();
-            //last two characters of e1_vec[1] are am or pm
-            let e1_ampm = &e1_vec[1][e1_vec[1].len()-2..];
-            let e2_ampm = &e2_vec[1][e2_vec[1].len()-2..];
-            //remove am or pm from e1_vec[1] and parse to int
-            let e1_min = e1_vec[1][0..e1_vec[1].len()-2].to_string().parse::<i32>().unwrap();
-            let e2_min = e2_vec[1][0..e2_vec[1].len()-2].to_string().parse::<i32>().unwrap();
-            if e1_hour == 12 && e1_ampm == "am" {
-                e1_hour = 0;
-            }
-            if e2_hour == 12 && e2_ampm == "am" {
-                e2_hour = 0;
-            }
-            if e1_hour == 12 && e1_ampm == "pm" {
-                e1_hour = 0;
-            }
-            if e2_hour == 12 && e2_ampm == "pm" {
-                e2_hour = 0;
-            }
-            if e1_ampm == "am" && e2_ampm == "pm" {
-                return std::cmp::Ordering::Less;
-            }
-            else if e1_ampm == "pm" && e2_ampm == "am" {
-                return std::cmp::Ordering::Greater;
-            }
-            else if (e1_ampm == "am" && e2_ampm == "am") || (e1_ampm == "pm" && e2_ampm == "pm") {
-                if e1_hour < e2_hour {
-                    return std::cmp::Ordering::Less;
-                }
-                else if e1_hour > e2_hour {
-                    return std::cmp::Ordering::Greater;
-                }
-                else {
-                    if e1_min < e2_min {
-                        return std::cmp::Ordering::Less;
-                    }
-                    else if e1_min > e2_min {
-                        return std::cmp::Ordering::Greater;
-                    }
-                    else {
-                        return std::cmp::Ordering::Equal;
-                    }
-                }
-            }
-            else {
-                return std::cmp::Ordering::Equal;
-            }
-        });
+        let mut todays_events = events_on(events, day);
+        store::sort_events(&mut todays_events);
         //make a table with start time + description of events in todays_events and place it in the box
         let rows = todays_events.iter().map(|event| Row::new(vec![
-            event.start.clone(),
+            dates::format_time(event.start),
             event.description.clone(),
         ]));
         let widths = [Constraint::Length(7), Constraint::Length(20)];
@@ -991,7 +1547,6 @@ fn ui(frame: &mut Frame, tasks: &mut Vec<Task>, events: &mut Vec<Event1>) {
             table = table.style(Style::default().fg(Color::Green).bg(Color::Black));
         }
         frame.render_widget(table, agenda_layout[i+1]);
-        day = day + Duration::days(1);
     }
     let block = Block::default().style(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD).bg(Color::Black));
     frame.render_widget(block, agenda_layout[0]);
@@ -1007,7 +1562,7 @@ fn ui(frame: &mut Frame, tasks: &mut Vec<Task>, events: &mut Vec<Event1>) {
 
     //border on top and bottom
     frame.render_widget(
-        Block::new().title("vayu dashboard - press 'q' to quit").title_alignment(Alignment::Center).style(Style::default().fg(Color::Blue).bg(Color::Black)),
+        Block::new().title("vayu dashboard - press 'b' for the board, 'q' to quit").title_alignment(Alignment::Center).style(Style::default().fg(Color::Blue).bg(Color::Black)),
         main_layout[0],
     );
 
@@ -1016,29 +1571,57 @@ fn ui(frame: &mut Frame, tasks: &mut Vec<Task>, events: &mut Vec<Event1>) {
     let block_padding = Block::default().style(Style::default().fg(Color::White).bg(Color::Black));
     frame.render_widget(block_padding, taskevents_layout[0]);
 
-    //rendering the task list
-    let rows = tasks.iter().map(|task| Row::new(vec![
-        task.id.to_string(),
-        task.due.clone(),
-        task.description.clone(),
-    ]));
-    let widths = [Constraint::Length(4), Constraint::Length(10), Constraint::Length(20)];
+    //rendering the task list, most urgent first with subtasks under their parent. waiting tasks stay hidden
+    let today = now.format("%Y-%m-%d").to_string();
+    let mut pending: Vec<Task> = tasks.iter().filter(|task| !task.done && !task.is_waiting(&today)).cloned().collect();
+    urgency::sort_by_urgency(&mut pending, now);
+    let shown: Vec<usize> = (0..pending.len()).collect();
+    let rows = tree::order(&pending, &shown).into_iter().map(|(i, depth)| {
+        let task = &pending[i];
+        let mut description = format!("{}{}", tree::indent(depth), task.labelled_description());
+        if let Some((finished, total)) = tree::progress(tasks, task.id) {
+            description.push_str(&format!(" [{}/{}]", finished, total));
+        }
+        if let Some((ticked, total)) = checklist::progress(task) {
+            description.push_str(&format!(" ({}/{} checked)", ticked, total));
+        }
+        if task.is_active() {
+            description.push_str(" (started)");
+        }
+        let row = Row::new(vec![
+            task.id.to_string(),
+            task.priority.map(|p| p.to_string()).unwrap_or_default(),
+            task.due_text(),
+            description,
+        ]);
+        //blocked tasks are greyed out since they can't be worked on yet
+        if deps::is_blocked(tasks, task) {
+            row.style(Style::default().fg(Color::DarkGray))
+        }
+        else {
+            row
+        }
+    });
+    let widths = [Constraint::Length(4), Constraint::Length(1), Constraint::Length(16), Constraint::Min(20)];
     let table = Table::new(rows, widths)
         .block(Block::default().title("Task List"))
-        .header(Row::new(vec!["  ", "  ", "  "]).bottom_margin(1).style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)))
+        .header(Row::new(vec!["  ", " ", "  ", "  "]).bottom_margin(1).style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)))
         .style(Style::default().fg(Color::White).bg(Color::Black))
         .highlight_style(Style::default().add_modifier(Modifier::BOLD))
         .highlight_symbol(">>");
     frame.render_stateful_widget(table, taskevents_layout[1], &mut table_state);
 
     //rendering the event list
-    let rows = events.iter().map(|event| Row::new(vec![
-        event.id.to_string(),
-        event.start.clone(),
-        event.end.clone(),
-        event.description.clone(),
-        event.repeat.clone().replace("monday,tuesday,wednesday,thursday,friday,saturday,sunday", "everyday").replace("monday,tuesday,wednesday,thursday,friday", "weekday").replace("saturday,sunday", "weekend"),
-    ]));
+    let rows = events.iter().map(|event| {
+        let (start, end) = event.times();
+        Row::new(vec![
+            event.id.to_string(),
+            start,
+            end,
+            event.description.clone(),
+            event.repeat.clone().replace("monday,tuesday,wednesday,thursday,friday,saturday,sunday", "everyday").replace("monday,tuesday,wednesday,thursday,friday", "weekday").replace("saturday,sunday", "weekend"),
+        ])
+    });
     let widths = [Constraint::Length(4), Constraint::Length(10), Constraint::Length(10), Constraint::Length(20), Constraint::Length(20)];
     let table = Table::new(rows, widths)
         .block(Block::default().title("Event1 List"))
@@ -1051,7 +1634,7 @@ fn ui(frame: &mut Frame, tasks: &mut Vec<Task>, events: &mut Vec<Event1>) {
 
 fn news() {
     //formatted string with https://www.google.com/search?client=firefox-b-1-d&q= and arg1
-    let search = format!("https://news.ycombinator.com/");
+    let search = "https://news.ycombinator.com/".to_string();
     let response = reqwest::blocking::get(
         search,
     )
@@ -1072,11 +1655,172 @@ fn news() {
         if text == "API" {
             break;
         }
-        if text != "" && href != "" && href != " " && href.contains("http") {
-            println!("");
+        if !text.is_empty() && !href.is_empty() && href != " " && href.contains("http") {
+            println!();
             println!("{}",text.green());
             println!("{}",href.blue());
         }
 
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::backend::TestBackend;
+
+    fn at(text: &str) -> DateTime<Local> {
+        clock::parse(text).unwrap()
+    }
+
+    fn event(description: &str, start: &str, repeat: &str) -> Event1 {
+        Event1 {
+            id: 1,
+            uuid: Uuid::new_v4(),
+            description: description.to_string(),
+            start: dates::parse_time(start).unwrap(),
+            end: dates::parse_time("11:00pm").unwrap(),
+            repeat: repeat.to_string(),
+            extra: Map::new(),
+        }
+    }
+
+    fn task(due: &str) -> Task {
+        Task::new(1, "task".to_string(), due.to_string())
+    }
+
+    #[test]
+    fn agenda_follows_the_day_of_the_week() {
+        let events = vec![
+            event("gym", "7:00am", "monday,wednesday,friday"),
+            event("standup", "9:00am", "monday,tuesday,wednesday,thursday,friday"),
+            event("brunch", "11:00am", "saturday,sunday"),
+            event("dentist", "3:00pm", "2024-06-13"),
+        ];
+        let cases = [
+            ("2024-06-10", vec!["gym", "standup"]),
+            ("2024-06-11", vec!["standup"]),
+            ("2024-06-12", vec!["gym", "standup"]),
+            ("2024-06-13", vec!["standup", "dentist"]),
+            ("2024-06-15", vec!["brunch"]),
+            ("2024-06-20", vec!["standup"]),
+        ];
+        for (day, expected) in cases {
+            let day = NaiveDate::parse_from_str(day, "%Y-%m-%d").unwrap();
+            let found: Vec<String> = events_on(&events, day).into_iter().map(|e| e.description).collect();
+            assert_eq!(found, expected, "events on {}", day);
+        }
+    }
+
+    #[test]
+    fn calendar_starts_yesterday() {
+        let days: Vec<String> = calendar_days(at("2024-03-01").date_naive()).iter().map(|d| d.to_string()).collect();
+        assert_eq!(days, ["2024-02-29", "2024-03-01", "2024-03-02", "2024-03-03", "2024-03-04", "2024-03-05", "2024-03-06"]);
+    }
+
+    #[test]
+    fn highlights_overdue_and_soon() {
+        let now = at("2024-06-12 10:00");
+        let due_at = |text: &str| {
+            let mut task = task(&text[..10]);
+            task.due_at = Some(at(text).fixed_offset());
+            task
+        };
+        let mut done = task("2024-06-01");
+        done.done = true;
+        let cases = [
+            (task("2024-06-11"), true),
+            (task("2024-06-12"), true),
+            (task("2024-06-13"), false),
+            (task(""), false),
+            (done, false),
+            (due_at("2024-06-12 08:00"), true),
+            (due_at("2024-06-13 09:00"), true),
+            (due_at("2024-06-13 11:00"), false),
+        ];
+        for (i, (task, expected)) in cases.iter().enumerate() {
+            assert_eq!(due_soon(task, now, 24), *expected, "case {} due {}", i, task.due_text());
+        }
+        assert!(!due_soon(&due_at("2024-06-13 09:00"), now, 4));
+    }
+
+    #[test]
+    fn list_flags_after_the_filter() {
+        let cases = [
+            ("+infra --sort id", "id", false, vec!["+infra"]),
+            ("+infra -@home --all", "due", true, vec!["+infra", "-@home"]),
+            ("--sort=urgency -chore", "urgency", false, vec!["-chore"]),
+            ("due.before:friday --soon 48 and +work", "due", false, vec!["due.before:friday", "and", "+work"]),
+            ("-- --all", "due", false, vec!["--all"]),
+        ];
+        for (input, expected_sort, expected_all, expected_filter) in cases {
+            let args = ["vayu", "list"].into_iter().chain(input.split(' ')).map(String::from).collect();
+            match SubComm::parse_from(list_flags_first(args)) {
+                SubComm::List{all, sort, filter, ..} => {
+                    assert_eq!(sort, expected_sort, "{}", input);
+                    assert_eq!(all, expected_all, "{}", input);
+                    assert_eq!(filter, expected_filter, "{}", input);
+                },
+                _ => panic!("{} didn't parse as list", input),
+            }
+        }
+    }
+
+    #[test]
+    fn help_goes_to_the_command() {
+        let cases = [
+            ("--help", "", true),
+            ("-h", "", true),
+            ("add --help", "add", true),
+            ("--data-dir /tmp/x list -h", "list", true),
+            ("list +infra", "list", false),
+        ];
+        for (input, command, help) in cases {
+            let matches = Arguments::parse_from(["vayu"].into_iter().chain(input.split(' ')));
+            assert_eq!((matches.command.as_str(), matches.help), (command, help), "{}", input);
+        }
+    }
+
+    //the dashboard as text, one line per row
+    fn render(now: &str, tasks: &mut [Task], events: &mut [Event1]) -> Vec<String> {
+        let mut terminal = Terminal::new(TestBackend::new(200, 40)).unwrap();
+        clock::set_for_test(at(now));
+        terminal.draw(|f| ui(f, tasks, events)).unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..buffer.area.height)
+            .map(|y| (0..buffer.area.width).map(|x| buffer.get(x, y).symbol()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn weekly_calendar_for_any_date() {
+        let mut events = vec![event("gym", "7:00am", "monday"), event("dentist", "3:00pm", "2025-01-01")];
+        let screen = render("2024-12-31 12:00", &mut [], &mut events).join("\n");
+        for title in ["2024-12-30 mon", "2024-12-31 tue", "2025-01-01 wed", "2025-01-05 sun"] {
+            assert!(screen.contains(title), "missing {}", title);
+        }
+        assert!(!screen.contains("2024-12-29"));
+        assert!(!screen.contains("2025-01-06"));
+        //events are drawn in their own day's box, under its title
+        let lines: Vec<&str> = screen.lines().collect();
+        let header = lines.iter().position(|line| line.contains("2024-12-30 mon")).unwrap();
+        let in_box = |title: &str, text: &str| {
+            let column = lines[header].find(title).unwrap();
+            lines[header + 1..].iter().any(|line| line.get(column..).is_some_and(|rest| rest.starts_with(text)))
+        };
+        assert!(in_box("2024-12-30", "7:00am"));
+        assert!(in_box("2025-01-01", "3:00pm"));
+        assert!(!in_box("2024-12-31", "7:00am"));
+        assert!(!in_box("2025-01-05", "3:00pm"));
+    }
+
+    #[test]
+    fn task_list_hides_tasks_until_their_wait_date() {
+        let mut waiting = task("2024-06-20");
+        waiting.description = "renew passport".to_string();
+        waiting.wait = Some("2024-06-15".to_string());
+        let mut tasks = vec![waiting];
+        assert!(!render("2024-06-12 09:00", &mut tasks, &mut []).join("\n").contains("renew passport"));
+        assert!(render("2024-06-15 09:00", &mut tasks, &mut []).join("\n").contains("renew passport"));
+    }
+}
@@ -0,0 +1,59 @@
+//works out where the task and event stores live.
+//order of precedence:
+//  1. --data-dir flag
+//  2. VAYU_HOME env var
+//  3. a project local `.vayu/` in the current directory or any parent (like git finds `.git`)
+//  4. $XDG_DATA_HOME/vayu (or the platform data dir when XDG_DATA_HOME isn't set)
+
+use std::env;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const PROJECT_DIR: &str = ".vayu";
+
+pub fn data_dir(flag: Option<PathBuf>) -> io::Result<PathBuf> {
+    if let Some(dir) = flag {
+        return Ok(dir);
+    }
+    if let Some(dir) = env::var_os("VAYU_HOME").filter(|d| !d.is_empty()) {
+        return Ok(PathBuf::from(dir));
+    }
+    if let Some(dir) = find_project_dir(&env::current_dir()?) {
+        return Ok(dir);
+    }
+    user_data_dir().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "could not find a data directory. use --data-dir or VAYU_HOME"))
+}
+
+//walks up from start looking for a `.vayu` directory
+pub fn find_project_dir(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join(PROJECT_DIR))
+        .find(|dir| dir.is_dir())
+}
+
+fn user_data_dir() -> Option<PathBuf> {
+    env::var_os("XDG_DATA_HOME")
+        .filter(|d| !d.is_empty())
+        .map(PathBuf::from)
+        .or_else(dirs::data_dir)
+        .map(|dir| dir.join("vayu"))
+}
+
+//strips the global --data-dir option so the subcommand parser only sees its own arguments.
+//like git, global options have to come before the command.
+pub fn subcommand_args() -> Vec<String> {
+    let mut args = Vec::new();
+    let mut iter = env::args();
+    args.extend(iter.next());
+    while let Some(arg) = iter.next() {
+        if arg == "--data-dir" {
+            iter.next();
+        } else if !arg.starts_with("--data-dir=") {
+            args.push(arg);
+            break;
+        }
+    }
+    args.extend(iter);
+    args
+}
@@ -0,0 +1,431 @@
+//query language for picking out tasks on the command line.
+//terms are combined with and, or, not and parentheses. terms next to each other are and-ed.
+//ex: vayu list +infra -@home
+//ex: vayu done 'due.before:today and +chore'
+//ex: vayu list '(priority:H or due:today) and not status:done'
+//
+//terms:
+//  +tag, -tag, @context, -@context
+//  due:DATE, due.before:DATE, due.after:DATE (due: alone matches tasks with no due date)
+//  priority:H (or pri:, H M L or 1 to 4, empty for no priority)
+//  status:pending, status:done
+//  state:NAME for a workflow state from config.toml, ex: state:review
+//  desc:text (case insensitive substring), desc~regex
+//  id:N
+//  scheduled:DATE, scheduled.before:DATE, scheduled.after:DATE
+//  ready (pending and not waiting on anything), blocked (waiting on a pending task)
+//  waiting (hidden until a wait date that hasn't come yet), active (time is being tracked)
+//  name:value, name.below:value, name.above:value for attributes declared in config.toml
+//  (name: alone matches tasks without a value, .before and .after work too)
+
+use crate::clock;
+use crate::config;
+use crate::dates;
+use crate::deps;
+use crate::edit;
+use crate::store::{self, Task};
+use crate::workflow;
+use regex::Regex;
+use serde_json::Value;
+use std::cmp::Ordering;
+
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Term(Term),
+}
+
+enum Term {
+    Tag(String),
+    Context(String),
+    Due(String),
+    DueBefore(String),
+    DueAfter(String),
+    Priority(Option<u8>),
+    Done(bool),
+    State(&'static str),
+    Desc(String),
+    DescRegex(Regex),
+    Id(i32),
+    Scheduled(String),
+    ScheduledBefore(String),
+    ScheduledAfter(String),
+    Ready,
+    Blocked,
+    Waiting,
+    Active,
+    //attribute name, the order the task's value must have against the value, and the value
+    Uda(String, Option<Ordering>, Option<Value>),
+}
+
+#[derive(Default)]
+pub struct Query {
+    expr: Option<Expr>,
+    //whether the query says which status it wants, so list shouldn't pick one for it
+    status: bool,
+    //whether the query asks for waiting tasks, which list otherwise hides
+    waiting: bool,
+}
+
+impl Query {
+    //each argument can hold several words, so a query can be passed quoted or not
+    pub fn parse(args: &[String]) -> Result<Query, String> {
+        let tokens = tokenize(args);
+        let status = tokens.iter().any(|t| t.starts_with("status:") || t.starts_with("state:") || t == "ready" || t == "blocked");
+        let waiting = tokens.iter().any(|t| t == "waiting");
+        let mut parser = Parser { tokens, pos: 0 };
+        if parser.tokens.is_empty() {
+            return Ok(Query::default());
+        }
+        let expr = parser.or()?;
+        if let Some(token) = parser.peek() {
+            return Err(format!("unexpected \"{}\" in query", token));
+        }
+        Ok(Query { expr: Some(expr), status, waiting })
+    }
+
+    //`tasks` is the whole list, needed to tell whether a task is blocked
+    pub fn matches(&self, tasks: &[Task], task: &Task) -> bool {
+        match &self.expr {
+            Some(expr) => eval(expr, tasks, task),
+            None => true,
+        }
+    }
+
+    pub fn mentions_status(&self) -> bool {
+        self.status
+    }
+
+    pub fn mentions_waiting(&self) -> bool {
+        self.waiting
+    }
+}
+
+//true when the argument is a single task id or uuid prefix rather than a query
+pub fn is_key(arg: &str) -> bool {
+    !arg.is_empty() && arg.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        && !arg.starts_with('-')
+        && !matches!(arg, "and" | "or" | "not" | "ready" | "blocked" | "waiting" | "active")
+}
+
+//the tasks `arg` picks out: one task by id or uuid prefix, or every task matching a query
+pub fn select(tasks: &[Task], arg: &str) -> Result<Vec<usize>, String> {
+    if is_key(arg) {
+        return store::find_task(tasks, arg).map(|index| vec![index]);
+    }
+    let query = Query::parse(&[arg.to_string()])?;
+    //like list, completed tasks are left out unless the query asks for a status
+    let selected: Vec<usize> = (0..tasks.len())
+        .filter(|&i| (query.mentions_status() || !tasks[i].done) && query.matches(tasks, &tasks[i]))
+        .collect();
+    if selected.is_empty() {
+        return Err(format!("no tasks match \"{}\"", arg));
+    }
+    Ok(selected)
+}
+
+//splits on whitespace and pulls parentheses out as their own tokens.
+//closing parentheses are only split off when unbalanced, so desc~(a|b) stays whole.
+fn tokenize(args: &[String]) -> Vec<String> {
+    let mut tokens = Vec::new();
+    for word in args.iter().flat_map(|arg| arg.split_whitespace()) {
+        let mut word = word;
+        while let Some(rest) = word.strip_prefix('(') {
+            tokens.push("(".to_string());
+            word = rest;
+        }
+        let mut closing = 0;
+        while word.ends_with(')') && word.matches(')').count() > word.matches('(').count() {
+            word = &word[..word.len() - 1];
+            closing += 1;
+        }
+        if !word.is_empty() {
+            tokens.push(word.to_string());
+        }
+        tokens.extend(std::iter::repeat_n(")".to_string(), closing));
+    }
+    tokens
+}
+
+struct Parser {
+    tokens: Vec<String>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(|t| t.as_str())
+    }
+
+    fn next(&mut self) -> Option<String> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    //or binds loosest, then and, then not
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.and()?;
+        while self.peek() == Some("or") {
+            self.next();
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.not()?;
+        loop {
+            match self.peek() {
+                Some("and") => {
+                    self.next();
+                },
+                Some("or") | Some(")") | None => break,
+                _ => {},
+            }
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(token) if token == "not" => Ok(Expr::Not(Box::new(self.not()?))),
+            Some(token) if token == "(" => {
+                let expr = self.or()?;
+                match self.next() {
+                    Some(token) if token == ")" => Ok(expr),
+                    _ => Err("missing \")\" in query".to_string()),
+                }
+            },
+            Some(token) => term(&token),
+            None => Err("query ends too early".to_string()),
+        }
+    }
+}
+
+fn term(token: &str) -> Result<Expr, String> {
+    match token {
+        "ready" => return Ok(Expr::Term(Term::Ready)),
+        "blocked" => return Ok(Expr::Term(Term::Blocked)),
+        "waiting" => return Ok(Expr::Term(Term::Waiting)),
+        "active" => return Ok(Expr::Term(Term::Active)),
+        _ => {},
+    }
+    if let Some((prefix, name)) = edit::label(token) {
+        let term = match prefix {
+            "+" | "-" => Term::Tag(name.to_string()),
+            _ => Term::Context(name.to_string()),
+        };
+        return Ok(if prefix.starts_with('-') { Expr::Not(Box::new(Expr::Term(term))) } else { Expr::Term(term) });
+    }
+    if let Some(pattern) = token.strip_prefix("desc~").or_else(|| token.strip_prefix("description~")) {
+        let regex = Regex::new(&format!("(?i){}", pattern)).map_err(|e| format!("bad regex in \"{}\": {}", token, e))?;
+        return Ok(Expr::Term(Term::DescRegex(regex)));
+    }
+    let (key, value) = token.split_once(':')
+        .ok_or_else(|| format!("invalid query term \"{}\"", token))?;
+    let term = match key {
+        "due" if value.is_empty() => Term::Due(String::new()),
+        "due" => Term::Due(date(value)?),
+        "due.before" => Term::DueBefore(date(value)?),
+        "due.after" => Term::DueAfter(date(value)?),
+        "scheduled" | "sched" if value.is_empty() => Term::Scheduled(String::new()),
+        "scheduled" | "sched" => Term::Scheduled(date(value)?),
+        "scheduled.before" | "sched.before" => Term::ScheduledBefore(date(value)?),
+        "scheduled.after" | "sched.after" => Term::ScheduledAfter(date(value)?),
+        "priority" | "pri" => Term::Priority(edit::parse_priority(value)?),
+        "status" => match value {
+            "pending" => Term::Done(false),
+            "done" | "completed" => Term::Done(true),
+            _ => return Err(format!("invalid status \"{}\". use pending or done", value)),
+        },
+        "state" => Term::State(workflow::parse(value)?),
+        "desc" | "description" => Term::Desc(value.to_lowercase()),
+        "id" => Term::Id(value.parse().map_err(|_| format!("invalid id \"{}\"", value))?),
+        _ => {
+            let (name, order) = match key.split_once('.') {
+                Some((name, "below" | "before")) => (name, Some(Ordering::Less)),
+                Some((name, "above" | "after")) => (name, Some(Ordering::Greater)),
+                Some(_) => return Err(format!("unknown query field \"{}\"", key)),
+                None => (key, None),
+            };
+            let uda = config::uda(name).ok_or_else(|| format!("unknown query field \"{}\"", key))?;
+            let value = match value {
+                "" if order.is_none() => None,
+                _ => Some(config::parse_value(uda, value).map_err(|e| format!("{}: {}", name, e))?),
+            };
+            Term::Uda(name.to_string(), order, value)
+        },
+    };
+    Ok(Expr::Term(term))
+}
+
+fn date(value: &str) -> Result<String, String> {
+    dates::parse_date(value, clock::now().date_naive())
+        .map(|date| date.format("%Y-%m-%d").to_string())
+        .map_err(|e| format!("{} in query", e))
+}
+
+fn eval(expr: &Expr, tasks: &[Task], task: &Task) -> bool {
+    match expr {
+        Expr::And(a, b) => eval(a, tasks, task) && eval(b, tasks, task),
+        Expr::Or(a, b) => eval(a, tasks, task) || eval(b, tasks, task),
+        Expr::Not(a) => !eval(a, tasks, task),
+        Expr::Term(term) => match term {
+            Term::Tag(name) => task.tags.contains(name),
+            Term::Context(name) => task.contexts.contains(name),
+            Term::Due(due) => &task.due == due,
+            //dates are YYYY-MM-DD so they compare as text. tasks without a due date never match
+            Term::DueBefore(due) => !task.due.is_empty() && &task.due < due,
+            Term::DueAfter(due) => !task.due.is_empty() && &task.due > due,
+            Term::Priority(priority) => &task.priority == priority,
+            Term::Done(done) => &task.done == done,
+            Term::State(state) => workflow::state(task) == *state,
+            Term::Desc(text) => task.description.to_lowercase().contains(text),
+            Term::DescRegex(regex) => regex.is_match(&task.description),
+            Term::Id(id) => &task.id == id,
+            Term::Scheduled(date) => task.scheduled.as_deref().unwrap_or("") == date,
+            Term::ScheduledBefore(date) => task.scheduled.as_deref().is_some_and(|s| s < date.as_str()),
+            Term::ScheduledAfter(date) => task.scheduled.as_deref().is_some_and(|s| s > date.as_str()),
+            Term::Ready => !task.done && !deps::is_blocked(tasks, task),
+            Term::Blocked => deps::is_blocked(tasks, task),
+            Term::Active => task.is_active(),
+            Term::Uda(name, order, value) => match (config::uda(name), task.extra.get(name), value) {
+                (_, current, None) => current.is_none(),
+                (_, None, Some(_)) => false,
+                (Some(uda), Some(current), Some(value)) => {
+                    let compared = config::compare(uda, Some(current), Some(value));
+                    match order {
+                        Some(order) => compared == *order,
+                        None => compared == Ordering::Equal,
+                    }
+                },
+                (None, _, _) => false,
+            },
+            Term::Waiting => task.is_waiting(&clock::now().format("%Y-%m-%d").to_string()),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(id: i32, description: &str, due: &str, labels: &str) -> Task {
+        let mut task = Task::new(id, description.to_string(), due.to_string());
+        for label in labels.split_whitespace() {
+            match label.split_at(1) {
+                ("+", tag) => task.tags.push(tag.to_string()),
+                ("@", context) => task.contexts.push(context.to_string()),
+                ("!", priority) => task.priority = edit::parse_priority(priority).unwrap(),
+                _ => task.done = label == "done",
+            }
+        }
+        task
+    }
+
+    //a wednesday, 2024-06-12
+    fn tasks() -> Vec<Task> {
+        clock::set_for_test(clock::parse("2024-06-12 09:00").unwrap());
+        vec![
+            task(1, "write report", "2024-06-12", "+work !H"),
+            task(2, "buy milk", "2024-06-14", "+home @store"),
+            task(3, "fix bike", "", "+home done"),
+            task(4, "call mom", "", "@phone !L"),
+        ]
+    }
+
+    #[test]
+    fn parses_and_matches() {
+        let tasks = tasks();
+        let cases: [(&str, &[i32]); 16] = [
+            ("", &[1, 2, 3, 4]),
+            ("+home", &[2, 3]),
+            ("+home -@store", &[3]),
+            ("+work or @phone", &[1, 4]),
+            ("not +home", &[1, 4]),
+            ("+work +home", &[]),
+            ("(+work or +home) and priority:H", &[1]),
+            ("+home or +work and priority:H", &[1, 2, 3]),
+            ("not +work and not +home", &[4]),
+            ("due:today", &[1]),
+            ("due.before:friday", &[1]),
+            ("due:", &[3, 4]),
+            ("status:done", &[3]),
+            ("desc:MILK", &[2]),
+            ("desc~^(buy|call)", &[2, 4]),
+            ("id:4 or pri:L", &[4]),
+        ];
+        for (input, expected) in cases {
+            let query = Query::parse(&[input.to_string()]).unwrap_or_else(|e| panic!("{:?}: {}", input, e));
+            let matched: Vec<i32> = tasks.iter().filter(|t| query.matches(&tasks, t)).map(|t| t.id).collect();
+            assert_eq!(matched, expected, "query {:?}", input);
+        }
+    }
+
+    #[test]
+    fn rejects_bad_queries() {
+        let cases = [
+            ("(+work", "missing \")\" in query"),
+            ("+work )", "unexpected \")\" in query"),
+            ("+work and", "query ends too early"),
+            ("bogus", "invalid query term \"bogus\""),
+            ("status:maybe", "invalid status \"maybe\". use pending or done"),
+            ("nope:1", "unknown query field \"nope\""),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(Query::parse(&[input.to_string()]).err().as_deref(), Some(expected), "query {:?}", input);
+        }
+        assert!(Query::parse(&["desc~(".to_string()]).is_err());
+        assert!(Query::parse(&["due:someday".to_string()]).is_err());
+    }
+
+    #[test]
+    fn tokenizes() {
+        let cases: [(&[&str], &[&str]); 4] = [
+            (&["(+a or +b) and not +c"], &["(", "+a", "or", "+b", ")", "and", "not", "+c"]),
+            (&["desc~(a|b)"], &["desc~(a|b)"]),
+            (&["((+a)", "+b)"], &["(", "(", "+a", ")", "+b", ")"]),
+            (&["+a", "  +b  "], &["+a", "+b"]),
+        ];
+        for (input, expected) in cases {
+            let args: Vec<String> = input.iter().map(|arg| arg.to_string()).collect();
+            assert_eq!(tokenize(&args), expected, "tokenize({:?})", input);
+        }
+    }
+
+    #[test]
+    fn tells_keys_from_queries() {
+        let cases = [
+            ("1", true),
+            ("12", true),
+            ("3fa2", true),
+            ("3fa2-9b", true),
+            ("", false),
+            ("+home", false),
+            ("-home", false),
+            ("ready", false),
+            ("and", false),
+            ("due:today", false),
+            ("id:4", false),
+            ("1 or 2", false),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(is_key(input), expected, "is_key({:?})", input);
+        }
+    }
+
+    #[test]
+    fn selects_pending_tasks_unless_asked() {
+        let tasks = tasks();
+        let ids = |arg: &str| select(&tasks, arg).map(|selected| selected.iter().map(|&i| tasks[i].id).collect::<Vec<_>>());
+        assert_eq!(ids("+home"), Ok(vec![2]));
+        assert_eq!(ids("+home and status:done"), Ok(vec![3]));
+        assert_eq!(ids("+home and (status:done or status:pending)"), Ok(vec![2, 3]));
+        //an id picks the task whatever its status
+        assert_eq!(ids("3"), Ok(vec![3]));
+        assert_eq!(ids("+home and -@store"), Err("no tasks match \"+home and -@store\"".to_string()));
+    }
+}
@@ -0,0 +1,350 @@
+//recurring tasks. the rule is a small subset of an iCalendar RRULE, stored in its canonical form,
+//ex: FREQ=WEEKLY;INTERVAL=2;BYDAY=MO for every other monday.
+//it can be typed with a shortcut for the frequency and lowercase parts,
+//ex: recur:weekly;interval=2;byday=mo or recur:monthly;bymonthday=1;count=12
+//
+//COUNT is how many instances are left, this one included. each new instance gets one less.
+
+use crate::config;
+use crate::dates;
+use crate::store::{CheckItem, Task};
+use chrono::{Datelike, Duration, Months, NaiveDate, Weekday};
+use std::fmt;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Freq {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+#[derive(Clone, PartialEq)]
+pub struct Rule {
+    pub freq: Freq,
+    pub interval: u32,
+    pub by_day: Vec<Weekday>,
+    //days of the month, negative counts from the end (-1 is the last day)
+    pub by_month_day: Vec<i32>,
+    pub until: Option<NaiveDate>,
+    pub count: Option<u32>,
+}
+
+const DAYS: [(&str, Weekday); 7] = [
+    ("MO", Weekday::Mon),
+    ("TU", Weekday::Tue),
+    ("WE", Weekday::Wed),
+    ("TH", Weekday::Thu),
+    ("FR", Weekday::Fri),
+    ("SA", Weekday::Sat),
+    ("SU", Weekday::Sun),
+];
+
+pub fn parse(text: &str) -> Result<Rule, String> {
+    let mut rule = Rule { freq: Freq::Daily, interval: 1, by_day: Vec::new(), by_month_day: Vec::new(), until: None, count: None };
+    let mut freq = None;
+    for (i, part) in text.trim().split(';').filter(|p| !p.is_empty()).enumerate() {
+        let part = part.trim().to_uppercase();
+        let (key, value) = match part.split_once('=') {
+            Some((key, value)) => (key.to_string(), value.to_string()),
+            //the first part can be the frequency on its own
+            None if i == 0 => ("FREQ".to_string(), part.clone()),
+            None => return Err(format!("bad recurrence part \"{}\", expected KEY=VALUE", part)),
+        };
+        match key.as_str() {
+            "FREQ" => {
+                freq = Some(match value.as_str() {
+                    "DAILY" => Freq::Daily,
+                    "WEEKLY" => Freq::Weekly,
+                    "MONTHLY" => Freq::Monthly,
+                    "YEARLY" => Freq::Yearly,
+                    //weekdays is a common enough chore schedule to get its own shortcut
+                    "WEEKDAYS" => {
+                        rule.by_day = DAYS[..5].iter().map(|(_, day)| *day).collect();
+                        Freq::Weekly
+                    },
+                    _ => return Err(format!("bad recurrence frequency \"{}\". use daily, weekly, monthly or yearly", value)),
+                });
+            },
+            "INTERVAL" => {
+                rule.interval = value.parse().ok().filter(|n| *n > 0)
+                    .ok_or_else(|| format!("bad recurrence interval \"{}\"", value))?;
+            },
+            "BYDAY" => {
+                for day in value.split(',') {
+                    let day = DAYS.iter()
+                        .find(|(name, weekday)| day == *name || day == weekday.to_string().to_uppercase() || full_name(*weekday) == day)
+                        .map(|(_, weekday)| *weekday)
+                        .ok_or_else(|| format!("bad recurrence day \"{}\". use MO, TU, WE, TH, FR, SA or SU", day))?;
+                    if !rule.by_day.contains(&day) {
+                        rule.by_day.push(day);
+                    }
+                }
+            },
+            "BYMONTHDAY" => {
+                for day in value.split(',') {
+                    let day: i32 = day.parse().ok().filter(|d: &i32| (1..=31).contains(&d.abs()))
+                        .ok_or_else(|| format!("bad recurrence month day \"{}\"", day))?;
+                    rule.by_month_day.push(day);
+                }
+            },
+            "UNTIL" => {
+                //20241231 or 20241231T235959Z, the date part of an iCalendar UNTIL
+                let until = NaiveDate::parse_from_str(&value, "%Y-%m-%d").ok()
+                    .or_else(|| NaiveDate::parse_from_str(value.get(..8)?, "%Y%m%d").ok())
+                    .ok_or_else(|| format!("bad recurrence end \"{}\", expected YYYY-MM-DD", value))?;
+                rule.until = Some(until);
+            },
+            "COUNT" => {
+                rule.count = Some(value.parse().ok().filter(|n| *n > 0)
+                    .ok_or_else(|| format!("bad recurrence count \"{}\"", value))?);
+            },
+            _ => return Err(format!("unknown recurrence part \"{}\"", key)),
+        }
+    }
+    rule.freq = freq.ok_or("recurrence needs a frequency, ex: recur:weekly")?;
+    if !rule.by_day.is_empty() && rule.freq != Freq::Weekly {
+        return Err("BYDAY only works with weekly recurrence".to_string());
+    }
+    if !rule.by_month_day.is_empty() && rule.freq != Freq::Monthly {
+        return Err("BYMONTHDAY only works with monthly recurrence".to_string());
+    }
+    rule.by_day.sort_by_key(|day| day.num_days_from_monday());
+    rule.by_month_day.sort();
+    Ok(rule)
+}
+
+fn full_name(day: Weekday) -> String {
+    NaiveDate::from_isoywd_opt(2024, 1, day).map(|d| d.format("%A").to_string().to_uppercase()).unwrap_or_default()
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let freq = match self.freq {
+            Freq::Daily => "DAILY",
+            Freq::Weekly => "WEEKLY",
+            Freq::Monthly => "MONTHLY",
+            Freq::Yearly => "YEARLY",
+        };
+        write!(f, "FREQ={}", freq)?;
+        if self.interval != 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+        if !self.by_day.is_empty() {
+            let days: Vec<&str> = self.by_day.iter()
+                .filter_map(|day| DAYS.iter().find(|(_, d)| d == day).map(|(name, _)| *name))
+                .collect();
+            write!(f, ";BYDAY={}", days.join(","))?;
+        }
+        if !self.by_month_day.is_empty() {
+            let days: Vec<String> = self.by_month_day.iter().map(|d| d.to_string()).collect();
+            write!(f, ";BYMONTHDAY={}", days.join(","))?;
+        }
+        if let Some(until) = self.until {
+            write!(f, ";UNTIL={}", until.format("%Y%m%d"))?;
+        }
+        if let Some(count) = self.count {
+            write!(f, ";COUNT={}", count)?;
+        }
+        Ok(())
+    }
+}
+
+//the first occurrence after `due` in the series that `due` belongs to
+fn after(rule: &Rule, due: NaiveDate) -> Option<NaiveDate> {
+    let interval = rule.interval as i64;
+    match rule.freq {
+        Freq::Daily => due.checked_add_signed(Duration::days(interval)),
+        Freq::Weekly if rule.by_day.is_empty() => due.checked_add_signed(Duration::weeks(interval)),
+        Freq::Weekly => {
+            //the listed days in this week, then in every interval-th week after it
+            let week_start = due - Duration::days(due.weekday().num_days_from_monday() as i64);
+            (1..=7 * (interval + 1)).map(|n| due + Duration::days(n)).find(|day| {
+                let weeks = (*day - week_start).num_days() / 7;
+                weeks % interval == 0 && rule.by_day.contains(&day.weekday())
+            })
+        },
+        Freq::Monthly => {
+            let days = if rule.by_month_day.is_empty() { vec![due.day() as i32] } else { rule.by_month_day.clone() };
+            let first = due.with_day(1)?;
+            //months without the day (the 31st in april) are skipped, so look a few intervals ahead
+            (0..48).step_by(rule.interval as usize).find_map(|n| {
+                let month = first.checked_add_months(Months::new(n))?;
+                let mut dates: Vec<NaiveDate> = days.iter().filter_map(|d| month_day(month, *d)).filter(|d| *d > due).collect();
+                dates.sort();
+                dates.first().copied()
+            })
+        },
+        Freq::Yearly => {
+            //february 29th only comes around in leap years
+            (1..=8).map(|n| n * rule.interval).find_map(|n| {
+                NaiveDate::from_ymd_opt(due.year() + n as i32, due.month(), due.day())
+            })
+        },
+    }
+}
+
+//a day of the month, counting from the end when negative. None if the month is too short
+fn month_day(first: NaiveDate, day: i32) -> Option<NaiveDate> {
+    if day > 0 {
+        first.with_day(day as u32)
+    }
+    else {
+        let last = first.checked_add_months(Months::new(1))? - Duration::days(1);
+        let date = last + Duration::days((day + 1) as i64);
+        if date.month() == first.month() { Some(date) } else { None }
+    }
+}
+
+//due date and rule for the instance after the one due on `due`. occurrences already in the past
+//are skipped, so finishing a chore late doesn't leave a trail of overdue copies.
+//None when the series is over.
+pub fn next(rule: &Rule, due: NaiveDate, today: NaiveDate) -> Option<(NaiveDate, Rule)> {
+    if rule.count == Some(1) {
+        return None;
+    }
+    let mut date = after(rule, due)?;
+    while date < today {
+        date = after(rule, date)?;
+    }
+    if rule.until.is_some_and(|until| date > until) {
+        return None;
+    }
+    let mut rule = rule.clone();
+    rule.count = rule.count.map(|count| count - 1);
+    Some((date, rule))
+}
+
+//short human form for listings, ex: "every 2 weeks on MO"
+pub fn describe(rule: &Rule) -> String {
+    let unit = match rule.freq {
+        Freq::Daily => "day",
+        Freq::Weekly => "week",
+        Freq::Monthly => "month",
+        Freq::Yearly => "year",
+    };
+    let mut text = if rule.interval == 1 { format!("every {}", unit) } else { format!("every {} {}s", rule.interval, unit) };
+    if !rule.by_day.is_empty() {
+        let days: Vec<String> = rule.by_day.iter().map(|day| day.to_string().to_lowercase()).collect();
+        text.push_str(&format!(" on {}", days.join(",")));
+    }
+    if !rule.by_month_day.is_empty() {
+        let days: Vec<String> = rule.by_month_day.iter()
+            .map(|d| if *d == -1 { "last".to_string() } else { d.to_string() })
+            .collect();
+        text.push_str(&format!(" on day {}", days.join(",")));
+    }
+    text
+}
+
+//the task to add when a recurring task is completed, or None if it doesn't recur or the series is over.
+//it keeps the description, priority, labels, parent and checklist but not dependencies, which were for this instance.
+pub fn next_instance(task: &Task, id: i32, today: NaiveDate) -> Option<Task> {
+    let rule = parse(task.recur.as_deref()?).ok()?;
+    //the series runs on the dates in the zone the due time was given in
+    let due = match task.due_at {
+        Some(due_at) => due_at.date_naive(),
+        None => NaiveDate::parse_from_str(&task.due, "%Y-%m-%d").unwrap_or(today),
+    };
+    let (due, rule) = next(&rule, due, today)?;
+    let mut instance = Task::new(id, task.description.clone(), due.format("%Y-%m-%d").to_string());
+    //a due time stays at the same clock time in its zone
+    instance.due_tz = task.due_tz.clone();
+    if let Some(due_at) = task.due_at {
+        let due_at = dates::localize(due, due_at.naive_local().time(), task.due_tz.as_deref()).ok()?;
+        instance.due = due_at.format("%Y-%m-%d").to_string();
+        instance.due_at = Some(due_at);
+    }
+    instance.priority = task.priority;
+    instance.tags = task.tags.clone();
+    instance.contexts = task.contexts.clone();
+    instance.parent = task.parent;
+    instance.recur = Some(rule.to_string());
+    //the steps start over, unticked
+    instance.checklist = task.checklist.iter().map(|item| CheckItem { text: item.text.clone(), done: false }).collect();
+    for (name, _) in config::udas() {
+        if let Some(value) = task.extra.get(name) {
+            instance.extra.insert(name.clone(), value.clone());
+        }
+    }
+    //wait and scheduled dates keep the same distance from the due date
+    let shift = |date: &Option<String>| {
+        let date = NaiveDate::parse_from_str(date.as_deref()?, "%Y-%m-%d").ok()?;
+        let old_due = NaiveDate::parse_from_str(&task.due, "%Y-%m-%d").ok()?;
+        Some((date + (due - old_due)).format("%Y-%m-%d").to_string())
+    };
+    instance.wait = shift(&task.wait);
+    instance.scheduled = shift(&task.scheduled);
+    Some(instance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn parses_rules() {
+        let cases = [
+            ("weekly", Ok("FREQ=WEEKLY")),
+            ("weekly;interval=2;byday=fr,mo", Ok("FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,FR")),
+            ("weekly;byday=monday,tue", Ok("FREQ=WEEKLY;BYDAY=MO,TU")),
+            ("weekdays", Ok("FREQ=WEEKLY;BYDAY=MO,TU,WE,TH,FR")),
+            ("monthly;bymonthday=15,-1;count=12", Ok("FREQ=MONTHLY;BYMONTHDAY=-1,15;COUNT=12")),
+            ("FREQ=YEARLY;UNTIL=2030-01-01", Ok("FREQ=YEARLY;UNTIL=20300101")),
+            ("FREQ=DAILY;UNTIL=20300101T000000Z", Ok("FREQ=DAILY;UNTIL=20300101")),
+            ("interval=2", Err("recurrence needs a frequency, ex: recur:weekly")),
+            ("hourly", Err("bad recurrence frequency \"HOURLY\". use daily, weekly, monthly or yearly")),
+            ("daily;byday=mo", Err("BYDAY only works with weekly recurrence")),
+            ("weekly;bymonthday=1", Err("BYMONTHDAY only works with monthly recurrence")),
+            ("monthly;bymonthday=32", Err("bad recurrence month day \"32\"")),
+            ("daily;interval=0", Err("bad recurrence interval \"0\"")),
+            ("daily;until=xÄÄÄÄ", Err("bad recurrence end \"XÄÄÄÄ\", expected YYYY-MM-DD")),
+            ("daily;until=2024", Err("bad recurrence end \"2024\", expected YYYY-MM-DD")),
+        ];
+        for (input, expected) in cases {
+            let parsed = parse(input).map(|rule| rule.to_string());
+            assert_eq!(parsed.as_deref(), expected.map_err(String::from).as_deref(), "parse({:?})", input);
+        }
+    }
+
+    #[test]
+    fn next_occurrences() {
+        //rule, due date, today, and the next due date with the count left, or None when the series is over
+        let cases = [
+            ("daily", "2024-06-12", "2024-06-12", Some(("2024-06-13", None))),
+            ("daily;interval=3", "2024-06-12", "2024-06-12", Some(("2024-06-15", None))),
+            ("weekly", "2024-06-12", "2024-06-12", Some(("2024-06-19", None))),
+            //the rest of this week first, then every other week
+            ("weekly;interval=2;byday=mo,fr", "2024-06-12", "2024-06-12", Some(("2024-06-14", None))),
+            ("weekly;interval=2;byday=mo,fr", "2024-06-14", "2024-06-14", Some(("2024-06-24", None))),
+            ("weekdays", "2024-06-14", "2024-06-14", Some(("2024-06-17", None))),
+            //february has no 31st
+            ("monthly", "2024-01-31", "2024-01-31", Some(("2024-03-31", None))),
+            ("monthly;bymonthday=-1", "2024-01-31", "2024-01-31", Some(("2024-02-29", None))),
+            ("monthly;bymonthday=-1", "2024-02-29", "2024-02-29", Some(("2024-03-31", None))),
+            ("monthly;bymonthday=1,15", "2024-06-01", "2024-06-01", Some(("2024-06-15", None))),
+            ("monthly;bymonthday=1,15", "2024-06-15", "2024-06-15", Some(("2024-07-01", None))),
+            ("monthly;interval=3", "2024-11-05", "2024-11-05", Some(("2025-02-05", None))),
+            ("yearly", "2024-02-29", "2024-02-29", Some(("2028-02-29", None))),
+            ("weekly;until=20240620", "2024-06-12", "2024-06-12", Some(("2024-06-19", None))),
+            ("weekly;until=20240620", "2024-06-19", "2024-06-19", None),
+            ("daily;count=3", "2024-06-12", "2024-06-12", Some(("2024-06-13", Some(2)))),
+            ("daily;count=1", "2024-06-12", "2024-06-12", None),
+            //occurrences already in the past are skipped, today's is kept
+            ("weekly", "2024-05-01", "2024-06-12", Some(("2024-06-12", None))),
+            ("daily", "2024-06-01", "2024-06-12", Some(("2024-06-12", None))),
+            ("daily;count=5", "2024-06-01", "2024-06-12", Some(("2024-06-12", Some(4)))),
+            ("weekly;until=20240610", "2024-05-01", "2024-06-12", None),
+        ];
+        for (rule, due, today, expected) in cases {
+            let next = next(&parse(rule).unwrap(), date(due), date(today));
+            let next = next.map(|(date, rule)| (date.format("%Y-%m-%d").to_string(), rule.count));
+            let expected = expected.map(|(date, count)| (date.to_string(), count));
+            assert_eq!(next, expected, "{} due {} on {}", rule, due, today);
+        }
+    }
+}
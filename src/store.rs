@@ -0,0 +1,1011 @@
+//task and event storage.
+//each store is a json lines file: the first line is a header with the schema version,
+//every following line is one record. unknown fields are kept in `extra` so that
+//older and newer vayu binaries can share the same files without dropping data.
+
+use crate::clock;
+use crate::config;
+use crate::dates;
+use crate::recur;
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, NaiveTime};
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+pub const TASKS_FILE: &str = "tasks.jsonl";
+pub const EVENTS_FILE: &str = "events.jsonl";
+pub const LEGACY_TASKS_FILE: &str = "tasks.txt";
+pub const LEGACY_EVENTS_FILE: &str = "events.txt";
+pub const ARCHIVE_FILE: &str = "archive.jsonl";
+pub const LOCK_FILE: &str = "vayu.lock";
+
+//how long to wait for another vayu process to finish before giving up
+const LOCK_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Task {
+    pub id: i32,
+    //stable identity that survives ids being reassigned by hand or by another tool
+    #[serde(default)]
+    pub uuid: Uuid,
+    pub description: String,
+    //the due date, YYYY-MM-DD or empty
+    pub due: String,
+    //the exact due time, when one was given. `due` is its date in the offset it was given in,
+    //so the record stays the same whichever time zone vayu runs in later
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due_at: Option<DateTime<FixedOffset>>,
+    //time zone the due time was given in (ex: Europe/Berlin), when it isn't the local one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due_tz: Option<String>,
+    //hidden from the list until this date, YYYY-MM-DD
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wait: Option<String>,
+    //when work is planned to start, YYYY-MM-DD
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scheduled: Option<String>,
+    #[serde(default)]
+    pub done: bool,
+    //workflow state from config.toml, ex: doing. None is the first state, or the last once done
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
+    //every move between workflow states, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub transitions: Vec<Transition>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<DateTime<Local>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<DateTime<Local>>,
+    //1 is the most important, 4 the least. H, M and L are 1, 2 and 3
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<u8>,
+    //area of work (+infra) and where it can be done (@home), stored without the prefix
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub contexts: Vec<String>,
+    //id of the task this is a subtask of
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<i32>,
+    //ids of the tasks that have to be done before this one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends: Vec<i32>,
+    //recurrence rule, ex: FREQ=WEEKLY;BYDAY=MO. completing the task adds the next instance
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recur: Option<String>,
+    //time worked on the task, from `vayu start` and `vayu stop`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub intervals: Vec<Interval>,
+    //timestamped notes from `vayu annotate`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub annotations: Vec<Annotation>,
+    //small steps ticked off with `vayu check`, too small to be tasks of their own
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub checklist: Vec<CheckItem>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Transition {
+    pub state: String,
+    pub at: DateTime<Local>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CheckItem {
+    pub text: String,
+    #[serde(default)]
+    pub done: bool,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Annotation {
+    pub at: DateTime<Local>,
+    pub text: String,
+}
+
+//one stretch of work on a task. `end` is None while the timer is running
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Interval {
+    pub start: DateTime<Local>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end: Option<DateTime<Local>>,
+}
+
+impl Task {
+    pub fn new(id: i32, description: String, due: String) -> Task {
+        Task {
+            id,
+            uuid: Uuid::new_v4(),
+            description,
+            due,
+            due_at: None,
+            due_tz: None,
+            wait: None,
+            scheduled: None,
+            done: false,
+            state: None,
+            transitions: Vec::new(),
+            completed_at: None,
+            created_at: Some(clock::now()),
+            priority: None,
+            tags: Vec::new(),
+            contexts: Vec::new(),
+            parent: None,
+            depends: Vec::new(),
+            recur: None,
+            intervals: Vec::new(),
+            annotations: Vec::new(),
+            checklist: Vec::new(),
+            extra: Map::new(),
+        }
+    }
+
+    //true while the task's timer is running
+    pub fn is_active(&self) -> bool {
+        self.intervals.last().is_some_and(|interval| interval.end.is_none())
+    }
+
+    //pending but hidden until its wait date
+    pub fn is_waiting(&self, today: &str) -> bool {
+        !self.done && self.wait.as_deref().is_some_and(|wait| wait > today)
+    }
+
+    //due date with the time in the local zone when there is one, ex: 2024-06-07 17:00
+    pub fn due_text(&self) -> String {
+        match self.due_at {
+            Some(due_at) => due_at.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string(),
+            None => self.due.clone(),
+        }
+    }
+
+    //description followed by its tags and contexts, the way they were typed
+    pub fn labelled_description(&self) -> String {
+        let mut text = self.description.clone();
+        for tag in &self.tags {
+            text.push_str(&format!(" +{}", tag));
+        }
+        for context in &self.contexts {
+            text.push_str(&format!(" @{}", context));
+        }
+        text
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Event1 {
+    pub id: i32,
+    #[serde(default)]
+    pub uuid: Uuid,
+    pub description: String,
+    #[serde(with = "event_time")]
+    pub start: NaiveTime,
+    #[serde(with = "event_time")]
+    pub end: NaiveTime,
+    #[serde(default)]
+    pub repeat: String,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl Event1 {
+    //start and end as shown to people, ex: 9:30am - 11:00am
+    pub fn times(&self) -> (String, String) {
+        (dates::format_time(self.start), dates::format_time(self.end))
+    }
+}
+
+//event times are written as HH:MM. stores from before times were typed have H:MMam,
+//which still reads and is written back in 24 hour form
+mod event_time {
+    use chrono::NaiveTime;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(time: &NaiveTime, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&time.format("%H:%M").to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveTime, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        crate::dates::parse_time(&text).map_err(serde::de::Error::custom)
+    }
+}
+
+//first line of every store file
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Header {
+    pub kind: String,
+    pub version: u32,
+    //next id to hand out. it only ever goes up so ids of finished or removed records are never reused
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub next_id: i32,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+fn is_zero(n: &i32) -> bool {
+    *n == 0
+}
+
+impl Header {
+    pub fn new(kind: &str) -> Header {
+        Header {
+            kind: kind.to_string(),
+            version: SCHEMA_VERSION,
+            next_id: 0,
+            extra: Map::new(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Access {
+    //no lock is taken, so reading never waits on a writer. files are replaced whole with a rename,
+    //so a reader never sees half of a write
+    Read,
+    //the exclusive lock is held until the store is dropped, covering the whole read-modify-write cycle.
+    //commands that ask something ask it before taking this lock, so a prompt never blocks other commands
+    Write,
+}
+
+pub struct Store {
+    pub dir: PathBuf,
+    pub tasks: Vec<Task>,
+    pub events: Vec<Event1>,
+    pub task_header: Header,
+    pub event_header: Header,
+    //raw lines that couldn't be loaded. they are written back untouched so nothing is lost
+    //before `vayu doctor --fix` moves them aside
+    skipped_tasks: Vec<String>,
+    skipped_events: Vec<String>,
+    //what loading had to warn about, ex: the records that were skipped
+    pub warnings: Vec<String>,
+    lock: Option<fs::File>,
+}
+
+impl Store {
+    //loads both stores from dir, migrating the legacy `%` files the first time
+    pub fn load(dir: &Path, access: Access) -> io::Result<Store> {
+        let store = Store::load_quietly(dir, access)?;
+        for warning in &store.warnings {
+            println!("{}", warning);
+        }
+        Ok(store)
+    }
+
+    //like load, but the warnings are only kept in `warnings`, for the tui to show without printing over the screen
+    pub fn load_quietly(dir: &Path, access: Access) -> io::Result<Store> {
+        fs::create_dir_all(dir)?;
+        let mut warnings = legacy_here(dir);
+        //migrating writes the stores, which needs the lock even for a read
+        let exclusive = access == Access::Write || legacy_pending(dir);
+        let mut store = Store::load_locked(dir, access, exclusive)?;
+        warnings.append(&mut store.warnings);
+        store.warnings = warnings;
+        Ok(store)
+    }
+
+    fn load_locked(dir: &Path, access: Access, exclusive: bool) -> io::Result<Store> {
+        let lock = if exclusive { Some(acquire_lock(dir)?) } else { None };
+        let migrated = exclusive && migrate_legacy(dir)?;
+        let mut warnings = Vec::new();
+        let (task_header, tasks, skipped_tasks) = read_tasks(&dir.join(TASKS_FILE), "tasks", &mut warnings)?;
+        //events with times that can't be read would break sorting in the agenda, so they are skipped too
+        let (event_header, events, skipped_events) = read_records::<Event1>(&dir.join(EVENTS_FILE), "events", |e| e.id, check_event_times, &mut warnings)?;
+        let mut store = Store {
+            dir: dir.to_path_buf(),
+            tasks,
+            events,
+            task_header,
+            event_header,
+            skipped_tasks,
+            skipped_events,
+            warnings,
+            lock,
+        };
+        //stores written before the id counter existed start counting after the highest id ever used,
+        //including tasks that have already been archived
+        let mut upgraded = false;
+        if store.task_header.next_id == 0 {
+            let (_, archived, _) = read_tasks(&dir.join(ARCHIVE_FILE), "archive", &mut store.warnings)?;
+            store.task_header.next_id = archived.iter().chain(&store.tasks).map(|t| t.id).max().unwrap_or(0) + 1;
+            upgraded = true;
+        }
+        if store.event_header.next_id == 0 {
+            store.event_header.next_id = store.events.iter().map(|e| e.id).max().unwrap_or(0) + 1;
+            upgraded = true;
+        }
+        for task in store.tasks.iter_mut().filter(|t| t.uuid.is_nil()) {
+            task.uuid = Uuid::new_v4();
+            upgraded = true;
+        }
+        for event in store.events.iter_mut().filter(|e| e.uuid.is_nil()) {
+            event.uuid = Uuid::new_v4();
+            upgraded = true;
+        }
+        if (migrated || upgraded) && !exclusive {
+            //read without the lock, start over with it to save the upgrade
+            drop(store);
+            return Store::load_locked(dir, access, true);
+        }
+        if migrated || upgraded {
+            store.save()?;
+        }
+        if access == Access::Read {
+            store.lock = None;
+        }
+        Ok(store)
+    }
+
+    pub fn save(&mut self) -> io::Result<()> {
+        if self.lock.is_none() {
+            return Err(io::Error::new(io::ErrorKind::PermissionDenied, "store was opened read only"));
+        }
+        self.task_header.next_id = self.next_task_id();
+        self.event_header.next_id = self.next_event_id();
+        write_records(&self.dir.join(TASKS_FILE), &self.task_header, &self.tasks, &self.skipped_tasks)?;
+        write_records(&self.dir.join(EVENTS_FILE), &self.event_header, &self.events, &self.skipped_events)?;
+        Ok(())
+    }
+
+    //moves tasks completed before `before` out of the task store and into the archive.
+    //the archive is written first so a crash can only ever duplicate a task, never lose it.
+    pub fn archive_tasks(&mut self, before: DateTime<Local>) -> io::Result<usize> {
+        let (old, keep): (Vec<Task>, Vec<Task>) = self.tasks.drain(..).partition(|task| {
+            task.done && task.completed_at.map(|at| at < before).unwrap_or(true)
+        });
+        self.tasks = keep;
+        self.add_to_archive(&old)?;
+        Ok(old.len())
+    }
+
+    //appends tasks to archive.jsonl. the task store isn't touched
+    pub fn add_to_archive(&self, tasks: &[Task]) -> io::Result<()> {
+        if tasks.is_empty() {
+            return Ok(());
+        }
+        let path = self.dir.join(ARCHIVE_FILE);
+        let (header, mut archived, skipped) = read_archive(&path)?;
+        archived.extend(tasks.iter().cloned());
+        write_records(&path, &header, &archived, &skipped)
+    }
+
+    //takes tasks back out of archive.jsonl, for undoing an archive
+    pub fn remove_from_archive(&self, uuids: &[Uuid]) -> io::Result<()> {
+        let path = self.dir.join(ARCHIVE_FILE);
+        let (header, mut archived, skipped) = read_archive(&path)?;
+        archived.retain(|task| !uuids.contains(&task.uuid));
+        write_records(&path, &header, &archived, &skipped)
+    }
+
+    //`vayu migrate`: adds the tasks.txt / events.txt found in `from` to the stores and returns the legacy
+    //files that were read, for the caller to rename once the stores are saved. records keep their old id
+    //unless it is or was in use here, since ids are never reused
+    pub fn import_legacy(&mut self, from: &Path) -> io::Result<(usize, usize, Vec<PathBuf>)> {
+        let mut read = Vec::new();
+        let mut tasks = Vec::new();
+        let mut events = Vec::new();
+        let path = from.join(LEGACY_TASKS_FILE);
+        if non_empty(&path) {
+            tasks = read_legacy_tasks(&path)?;
+            read.push(path);
+        }
+        let path = from.join(LEGACY_EVENTS_FILE);
+        if non_empty(&path) {
+            events = read_legacy_events(&path)?;
+            read.push(path);
+        }
+        let mut next = self.next_task_id();
+        for task in &mut tasks {
+            if task.id < next {
+                task.id = next;
+            }
+            next = task.id + 1;
+        }
+        let mut next = self.next_event_id();
+        for event in &mut events {
+            if event.id < next {
+                event.id = next;
+            }
+            next = event.id + 1;
+        }
+        let counts = (tasks.len(), events.len());
+        self.tasks.append(&mut tasks);
+        self.events.append(&mut events);
+        Ok((counts.0, counts.1, read))
+    }
+
+    //next id to be used comes from the persisted counter. ids added by hand above it move it along
+    pub fn next_task_id(&self) -> i32 {
+        let highest = self.tasks.iter().map(|t| t.id).max().unwrap_or(0);
+        self.task_header.next_id.max(highest + 1)
+    }
+
+    pub fn next_event_id(&self) -> i32 {
+        let highest = self.events.iter().map(|e| e.id).max().unwrap_or(0);
+        self.event_header.next_id.max(highest + 1)
+    }
+}
+
+//finds a task by its numeric id or a unique prefix of its uuid
+pub fn find_task(tasks: &[Task], key: &str) -> Result<usize, String> {
+    find_by_key(tasks.iter().map(|t| (t.id, t.uuid)), key, "task")
+}
+
+pub fn find_event(events: &[Event1], key: &str) -> Result<usize, String> {
+    find_by_key(events.iter().map(|e| (e.id, e.uuid)), key, "event")
+}
+
+//earliest first, shorter events first when two start together
+pub fn sort_events(events: &mut [Event1]) {
+    events.sort_by_key(|event| (event.start, event.end));
+}
+
+//shortest key taken as the start of a uuid, so a one letter typo can't pick some random task
+const MIN_UUID_PREFIX: usize = 4;
+
+fn find_by_key(items: impl Iterator<Item = (i32, Uuid)>, key: &str, kind: &str) -> Result<usize, String> {
+    let items: Vec<(i32, Uuid)> = items.collect();
+    let key = key.trim();
+    if key.is_empty() {
+        return Err(format!("no {} id given", kind));
+    }
+    //a plain number is always tried as an id first. ids aren't reused, so a short number that
+    //isn't an id is a stale or mistyped one and must not fall through to some uuid that starts with it
+    let all_digits = key.chars().all(|c| c.is_ascii_digit());
+    if all_digits {
+        if let Some(index) = key.parse::<i32>().ok().and_then(|id| items.iter().position(|(item_id, _)| *item_id == id)) {
+            return Ok(index);
+        }
+    }
+    if key.len() < MIN_UUID_PREFIX {
+        return Err(match all_digits {
+            true => format!("{} with id {} not found", kind, key),
+            false => format!("\"{}\" is too short for a uuid prefix, use at least {} characters", key, MIN_UUID_PREFIX),
+        });
+    }
+    let prefix = key.to_lowercase();
+    let matches: Vec<usize> = items.iter().enumerate()
+        .filter(|(_, (_, uuid))| uuid.to_string().starts_with(&prefix))
+        .map(|(i, _)| i)
+        .collect();
+    match matches.as_slice() {
+        [index] => Ok(*index),
+        [] => Err(format!("{} with id {} not found", kind, key)),
+        _ => {
+            let ids: Vec<String> = matches.iter().map(|&i| items[i].0.to_string()).collect();
+            Err(format!("{} is ambiguous, it matches {}s {}", key, kind, ids.join(", ")))
+        }
+    }
+}
+
+//first 8 characters of a uuid, enough to address a record in practice
+pub fn short_uuid(uuid: &Uuid) -> String {
+    uuid.to_string()[..8].to_string()
+}
+
+//checks shared by loading and `vayu doctor`. the error is a short reason for the report.
+pub fn check_task(task: &Task) -> Result<(), String> {
+    if !task.due.is_empty() && NaiveDate::parse_from_str(&task.due, "%Y-%m-%d").is_err() {
+        return Err(format!("unparseable due date \"{}\"", task.due));
+    }
+    if let Some(due_at) = task.due_at {
+        if task.due != due_at.format("%Y-%m-%d").to_string() {
+            return Err(format!("due date {} doesn't match due time {}", task.due, due_at.to_rfc3339()));
+        }
+    }
+    if let Some(zone) = &task.due_tz {
+        dates::parse_zone(zone)?;
+    }
+    for date in task.wait.iter().chain(&task.scheduled) {
+        if NaiveDate::parse_from_str(date, "%Y-%m-%d").is_err() {
+            return Err(format!("unparseable date \"{}\"", date));
+        }
+    }
+    if let Some(priority) = task.priority.filter(|p| !(1..=4).contains(p)) {
+        return Err(format!("priority {} is out of range, expected 1 to 4", priority));
+    }
+    if let Some(name) = task.tags.iter().chain(&task.contexts).find(|name| !valid_label(name)) {
+        return Err(format!("bad tag or context \"{}\"", name));
+    }
+    if task.parent == Some(task.id) {
+        return Err("task is its own parent".to_string());
+    }
+    if task.depends.contains(&task.id) {
+        return Err("task depends on itself".to_string());
+    }
+    if let Some(rule) = &task.recur {
+        recur::parse(rule)?;
+    }
+    for interval in &task.intervals {
+        if interval.end.is_some_and(|end| end < interval.start) {
+            return Err(format!("time interval starting {} ends before it starts", interval.start.to_rfc3339()));
+        }
+    }
+    if task.intervals.iter().rev().skip(1).any(|interval| interval.end.is_none()) {
+        return Err("only the last time interval can still be running".to_string());
+    }
+    if task.checklist.iter().any(|item| item.text.trim().is_empty()) {
+        return Err("checklist item without text".to_string());
+    }
+    config::check_task(task)?;
+    Ok(())
+}
+
+pub fn check_event_times(event: &Event1) -> Result<(), String> {
+    if event.end <= event.start {
+        let (start, end) = event.times();
+        return Err(format!("event ends at {}, which isn't after its start at {}", end, start));
+    }
+    Ok(())
+}
+
+pub fn check_event(event: &Event1) -> Result<(), String> {
+    check_event_times(event)?;
+    let valid_repeat = NaiveDate::parse_from_str(&event.repeat, "%Y-%m-%d").is_ok()
+        || event.repeat.split(',').all(|day| dates::weekday(day).is_some());
+    if !valid_repeat {
+        return Err(format!("bad repeat \"{}\"", event.repeat));
+    }
+    Ok(())
+}
+
+//tag and context names start with a letter and don't contain spaces or prefixes
+pub fn valid_label(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_alphabetic())
+        && name.chars().all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.' | '/'))
+}
+
+//takes the exclusive advisory lock on the data dir for writing.
+//the lock file holds the pid of the writer so a blocked process can say who it is waiting on.
+pub fn acquire_lock(dir: &Path) -> io::Result<fs::File> {
+    let path = dir.join(LOCK_FILE);
+    let mut file = fs::OpenOptions::new().read(true).write(true).create(true).truncate(false).open(&path)?;
+    let start = Instant::now();
+    while FileExt::try_lock_exclusive(&file).is_err() {
+        if start.elapsed() > LOCK_TIMEOUT {
+            let mut owner = String::new();
+            //on windows the lock is mandatory so the pid may not be readable
+            file.read_to_string(&mut owner).ok();
+            let owner = match owner.trim() {
+                "" => "another vayu process".to_string(),
+                pid => format!("another vayu process (pid {})", pid),
+            };
+            return Err(io::Error::new(
+                io::ErrorKind::WouldBlock,
+                format!("{} is using {}. try again once it has finished", owner, dir.display()),
+            ));
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    file.set_len(0)?;
+    file.rewind()?;
+    write!(file, "{}", std::process::id())?;
+    file.flush()?;
+    Ok(file)
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+//tasks that were moved out with `vayu archive`
+pub fn load_archive(dir: &Path) -> io::Result<Vec<Task>> {
+    Ok(read_archive(&dir.join(ARCHIVE_FILE))?.1)
+}
+
+fn read_archive(path: &Path) -> io::Result<(Header, Vec<Task>, Vec<String>)> {
+    let mut warnings = Vec::new();
+    let archive = read_tasks(path, "archive", &mut warnings)?;
+    for warning in warnings {
+        println!("{}", warning);
+    }
+    Ok(archive)
+}
+
+fn read_tasks(path: &Path, kind: &str, warnings: &mut Vec<String>) -> io::Result<(Header, Vec<Task>, Vec<String>)> {
+    read_records::<Task>(path, kind, |t| t.id, check_task, warnings)
+}
+
+//records that don't parse, don't pass `check` or reuse an id already seen are skipped with a warning added to `warnings`.
+//a second record with the same id would otherwise be changed along with the first by every command
+fn read_records<T: serde::de::DeserializeOwned>(
+    path: &Path,
+    kind: &str,
+    id: impl Fn(&T) -> i32,
+    check: impl Fn(&T) -> Result<(), String>,
+    warnings: &mut Vec<String>,
+) -> io::Result<(Header, Vec<T>, Vec<String>)> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok((Header::new(kind), Vec::new(), Vec::new())),
+        Err(e) => return Err(e),
+    };
+    let mut lines = contents.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+    let header: Header = match lines.next() {
+        Some((_, line)) => serde_json::from_str(line)
+            .map_err(|e| invalid(format!("{}: bad header: {}. run `vayu doctor --fix` to repair it", path.display(), e)))?,
+        None => return Ok((Header::new(kind), Vec::new(), Vec::new())),
+    };
+    if header.kind != kind {
+        return Err(invalid(format!("{}: expected a {} store, found {}", path.display(), kind, header.kind)));
+    }
+    let mut records = Vec::new();
+    let mut skipped = Vec::new();
+    let mut seen = HashSet::new();
+    for (i, line) in lines {
+        match parse_record(line, &id, &check, &mut seen) {
+            Ok(record) => records.push(record),
+            Err(e) => {
+                warnings.push(format!("warning: skipping bad record on line {} of {}: {}. run `vayu doctor` for details", i + 1, path.display(), e));
+                skipped.push(line.to_string());
+            }
+        }
+    }
+    Ok((header, records, skipped))
+}
+
+//one line of a store. the id only counts as taken once the record has passed `check`, so a bad
+//record doesn't push out a good one with the same id. shared with `vayu doctor` so both agree
+pub fn parse_record<T: serde::de::DeserializeOwned>(
+    line: &str,
+    id: impl Fn(&T) -> i32,
+    check: impl Fn(&T) -> Result<(), String>,
+    seen: &mut HashSet<i32>,
+) -> Result<T, String> {
+    let record = serde_json::from_str::<T>(line).map_err(|e| e.to_string())?;
+    check(&record)?;
+    if !seen.insert(id(&record)) {
+        return Err(format!("duplicate id {}", id(&record)));
+    }
+    Ok(record)
+}
+
+fn write_records<T: Serialize>(path: &Path, header: &Header, records: &[T], skipped: &[String]) -> io::Result<()> {
+    let mut out = Vec::new();
+    let mut header = header.clone();
+    //never downgrade a store written by a newer vayu
+    header.version = header.version.max(SCHEMA_VERSION);
+    serde_json::to_writer(&mut out, &header)?;
+    out.push(b'\n');
+    for record in records {
+        serde_json::to_writer(&mut out, record)?;
+        out.push(b'\n');
+    }
+    for line in skipped {
+        out.extend_from_slice(line.as_bytes());
+        out.push(b'\n');
+    }
+    write_atomic(path, &out)
+}
+
+//writes to a temp file next to path and renames it over the original,
+//so a crash part way through never leaves a truncated store behind
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut tmp_name = path.as_os_str().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp = PathBuf::from(tmp_name);
+    let mut file = fs::File::create(&tmp)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+//older versions kept tasks.txt / events.txt in whatever directory vayu was run from.
+//files in the data dir are migrated automatically. a file of that name in the current directory
+//could be anything, so it's pointed out and `vayu migrate` imports it once asked to.
+//there's nothing to do once `store` exists or for an empty file.
+fn find_legacy(dir: &Path, name: &str, store: &str) -> Option<PathBuf> {
+    let path = dir.join(name);
+    (!dir.join(store).exists() && non_empty(&path)).then_some(path)
+}
+
+fn legacy_pending(dir: &Path) -> bool {
+    find_legacy(dir, LEGACY_TASKS_FILE, TASKS_FILE).is_some() || find_legacy(dir, LEGACY_EVENTS_FILE, EVENTS_FILE).is_some()
+}
+
+//a hint for each old file in the current directory
+fn legacy_here(dir: &Path) -> Vec<String> {
+    [(LEGACY_TASKS_FILE, TASKS_FILE), (LEGACY_EVENTS_FILE, EVENTS_FILE)].into_iter()
+        .filter(|(name, store)| {
+            let here = Path::new(name);
+            !dir.join(store).exists() && non_empty(here) && fs::canonicalize(here).ok() != fs::canonicalize(dir.join(name)).ok()
+        })
+        .map(|(name, _)| format!("found an old {} in the current directory. run `vayu migrate` to import it", name))
+        .collect()
+}
+
+fn non_empty(path: &Path) -> bool {
+    fs::metadata(path).map(|m| m.is_file() && m.len() > 0).unwrap_or(false)
+}
+
+//one time conversion of tasks.txt / events.txt into the json stores.
+//the old files are kept next to the new ones with a `.migrated` suffix.
+fn migrate_legacy(dir: &Path) -> io::Result<bool> {
+    let mut migrated = false;
+    if let Some(legacy_tasks) = find_legacy(dir, LEGACY_TASKS_FILE, TASKS_FILE) {
+        let tasks = read_legacy_tasks(&legacy_tasks)?;
+        write_records(&dir.join(TASKS_FILE), &Header::new("tasks"), &tasks, &[])?;
+        fs::rename(&legacy_tasks, legacy_tasks.with_extension("txt.migrated"))?;
+        println!("migrated {} tasks from {}", tasks.len(), legacy_tasks.display());
+        migrated = true;
+    }
+    if let Some(legacy_events) = find_legacy(dir, LEGACY_EVENTS_FILE, EVENTS_FILE) {
+        let events = read_legacy_events(&legacy_events)?;
+        write_records(&dir.join(EVENTS_FILE), &Header::new("events"), &events, &[])?;
+        fs::rename(&legacy_events, legacy_events.with_extension("txt.migrated"))?;
+        println!("migrated {} events from {}", events.len(), legacy_events.display());
+        migrated = true;
+    }
+    Ok(migrated)
+}
+
+//description%due%done%id
+fn read_legacy_tasks(path: &Path) -> io::Result<Vec<Task>> {
+    parse_legacy(path, 4, |fields| {
+        let mut task = Task::new(fields[3].trim().parse().ok()?, fields[0].to_string(), fields[1].to_string());
+        task.done = fields[2].trim().parse().ok()?;
+        //the old format didn't record when a task was made
+        task.created_at = None;
+        Some(task)
+    })
+}
+
+//description%start%end%repeat%id
+fn read_legacy_events(path: &Path) -> io::Result<Vec<Event1>> {
+    parse_legacy(path, 5, |fields| {
+        Some(Event1 {
+            id: fields[4].trim().parse().ok()?,
+            uuid: Uuid::new_v4(),
+            description: fields[0].to_string(),
+            start: dates::parse_time(fields[1]).ok()?,
+            end: dates::parse_time(fields[2]).ok()?,
+            repeat: fields[3].to_string(),
+            extra: Map::new(),
+        })
+    })
+}
+
+//the description is the only free text field and always comes first, so the line is
+//split from the right. this keeps descriptions that contain `%` intact.
+fn parse_legacy<T>(path: &Path, n_fields: usize, build: impl Fn(&[&str]) -> Option<T>) -> io::Result<Vec<T>> {
+    let contents = fs::read_to_string(path)?;
+    let mut records = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut fields: Vec<&str> = line.rsplitn(n_fields, '%').collect();
+        fields.reverse();
+        match (fields.len() == n_fields).then(|| build(&fields)).flatten() {
+            Some(record) => records.push(record),
+            None => println!("warning: skipping malformed line {} in {}", i + 1, path.display()),
+        }
+    }
+    Ok(records)
+}
+
+//an empty data directory of its own for each test
+#[cfg(test)]
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("vayu-test-{}-{}", name, Uuid::new_v4()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_bad_and_duplicate_tasks() {
+        let dir = temp_dir("skips");
+        let lines = [
+            r#"{"kind":"tasks","version":1,"next_id":4}"#,
+            r#"{"id":1,"description":"first","due":""}"#,
+            r#"{"id":1,"description":"same id","due":""}"#,
+            r#"{"id":2,"description":"bad due","due":"friday"}"#,
+            r#"{"id":3,"description":"bad wait","due":"","wait":"2024-13-01"}"#,
+        ];
+        fs::write(dir.join(TASKS_FILE), lines.join("\n")).unwrap();
+        let mut store = Store::load_quietly(&dir, Access::Write).unwrap();
+        let descriptions: Vec<&str> = store.tasks.iter().map(|t| t.description.as_str()).collect();
+        assert_eq!(descriptions, ["first"]);
+        //one warning per skipped line, kept for the caller to show
+        assert_eq!(store.warnings.len(), 3);
+        for line in 3..=5 {
+            assert!(store.warnings.iter().any(|w| w.contains(&format!("line {} of", line))), "no warning for line {}", line);
+        }
+        //the skipped lines are kept for `vayu doctor --fix`
+        store.save().unwrap();
+        let saved = fs::read_to_string(dir.join(TASKS_FILE)).unwrap();
+        assert!(lines[2..].iter().all(|line| saved.contains(line)));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn due_times_load_in_any_zone() {
+        let dir = temp_dir("zones");
+        //00:30 in berlin is still the day before in utc, 20:00 in new york already the day after.
+        //no single local zone has both dates, so checking against the local one dropped one of them
+        let lines = [
+            r#"{"kind":"tasks","version":1,"next_id":4}"#,
+            r#"{"id":1,"description":"late call","due":"2026-10-20","due_at":"2026-10-20T00:30:00+02:00","due_tz":"Europe/Berlin"}"#,
+            r#"{"id":2,"description":"evening call","due":"2026-10-19","due_at":"2026-10-19T20:00:00-04:00"}"#,
+            r#"{"id":3,"description":"wrong date","due":"2026-10-21","due_at":"2026-10-19T20:00:00-04:00"}"#,
+        ];
+        fs::write(dir.join(TASKS_FILE), lines.join("\n")).unwrap();
+        let store = Store::load_quietly(&dir, Access::Read).unwrap();
+        let ids: Vec<i32> = store.tasks.iter().map(|t| t.id).collect();
+        assert_eq!(ids, [1, 2]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn migrates_legacy_files_in_the_data_dir() {
+        let dir = temp_dir("migrate");
+        fs::write(dir.join(LEGACY_TASKS_FILE), "pay rent%2024-07-01%false%1\n50% off sale%2024-06-20%true%2\nbroken line\n").unwrap();
+        fs::write(dir.join(LEGACY_EVENTS_FILE), "gym%7:00am%8:00am%monday,friday%1\n").unwrap();
+        let store = Store::load(&dir, Access::Read).unwrap();
+        let tasks: Vec<(i32, &str, &str, bool)> = store.tasks.iter().map(|t| (t.id, t.description.as_str(), t.due.as_str(), t.done)).collect();
+        assert_eq!(tasks, [(1, "pay rent", "2024-07-01", false), (2, "50% off sale", "2024-06-20", true)]);
+        assert_eq!(store.events.len(), 1);
+        assert_eq!(store.events[0].times(), ("7:00am".to_string(), "8:00am".to_string()));
+        assert_eq!(store.task_header.next_id, 3);
+        //the originals are kept, and a second load doesn't import them again
+        assert!(dir.join("tasks.txt.migrated").exists() && dir.join("events.txt.migrated").exists());
+        assert!(!dir.join(LEGACY_TASKS_FILE).exists());
+        fs::write(dir.join(LEGACY_TASKS_FILE), "again%2024-07-01%false%9\n").unwrap();
+        assert_eq!(Store::load(&dir, Access::Read).unwrap().tasks.len(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn reads_legacy_lines() {
+        let dir = temp_dir("legacy");
+        let path = dir.join(LEGACY_TASKS_FILE);
+        let lines = [
+            "pay rent%2024-07-01%false%1",
+            "call bob, alice and carol%2024-07-02%true%2",
+            "100%, no 50% off%2024-07-03%false%3",
+            "",
+            "no id%2024-07-04%false",
+            "bad done%2024-07-04%maybe%5",
+        ];
+        fs::write(&path, lines.join("\n")).unwrap();
+        let tasks: Vec<(i32, String, String, bool)> = read_legacy_tasks(&path).unwrap().into_iter()
+            .map(|t| (t.id, t.description, t.due, t.done))
+            .collect();
+        let expected = [
+            (1, "pay rent", "2024-07-01", false),
+            (2, "call bob, alice and carol", "2024-07-02", true),
+            (3, "100%, no 50% off", "2024-07-03", false),
+        ];
+        assert_eq!(tasks, expected.map(|(id, d, due, done)| (id, d.to_string(), due.to_string(), done)));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn imports_legacy_files_from_elsewhere() {
+        let dir = temp_dir("import");
+        let old = temp_dir("import-old");
+        fs::write(old.join(LEGACY_TASKS_FILE), "first%2024-07-01%false%1\nsecond%2024-07-02%false%2\nlater%2024-07-03%false%9\n").unwrap();
+        let mut store = Store::load(&dir, Access::Write).unwrap();
+        store.tasks.push(Task::new(store.next_task_id(), "already here".to_string(), String::new()));
+        let (tasks, events, read) = store.import_legacy(&old).unwrap();
+        assert_eq!((tasks, events, read), (2 + 1, 0, vec![old.join(LEGACY_TASKS_FILE)]));
+        //id 1 is taken, so the old ones move up. 9 is free and kept
+        let ids: Vec<(i32, &str)> = store.tasks.iter().map(|t| (t.id, t.description.as_str())).collect();
+        assert_eq!(ids, [(1, "already here"), (2, "first"), (3, "second"), (9, "later")]);
+        fs::remove_dir_all(&dir).unwrap();
+        fs::remove_dir_all(&old).unwrap();
+    }
+
+    #[test]
+    fn finds_by_id_or_uuid_prefix() {
+        let uuid = |text: &str| Uuid::parse_str(text).unwrap();
+        let items = [
+            (3, uuid("9a1b2c3d-0000-4000-8000-000000000000")),
+            (12, uuid("12345678-0000-4000-8000-000000000000")),
+            (40, uuid("9a1b0000-0000-4000-8000-000000000000")),
+        ];
+        let cases = [
+            ("3", Ok(0)),
+            (" 12 ", Ok(1)),
+            //task 9 doesn't exist, task 3's uuid starting with 9 doesn't count
+            ("9", Err("task with id 9 not found")),
+            ("123", Err("task with id 123 not found")),
+            ("1234", Ok(1)),
+            ("9a1b2", Ok(0)),
+            ("9A1B0", Ok(2)),
+            ("9a1b", Err("9a1b is ambiguous, it matches tasks 3, 40")),
+            //letters are never an id, but still need 4 of them
+            ("b", Err("\"b\" is too short for a uuid prefix, use at least 4 characters")),
+            ("9a1", Err("\"9a1\" is too short for a uuid prefix, use at least 4 characters")),
+            ("beef", Err("task with id beef not found")),
+            ("", Err("no task id given")),
+        ];
+        for (key, expected) in cases {
+            let found = find_by_key(items.iter().copied(), key, "task");
+            assert_eq!(found, expected.map_err(|e| e.to_string()), "key {:?}", key);
+        }
+    }
+
+    fn event(start: &str, end: &str) -> Event1 {
+        Event1 {
+            id: 1,
+            uuid: Uuid::new_v4(),
+            description: format!("{} - {}", start, end),
+            start: dates::parse_time(start).unwrap(),
+            end: dates::parse_time(end).unwrap(),
+            repeat: "monday".to_string(),
+            extra: Map::new(),
+        }
+    }
+
+    #[test]
+    fn events_sort_by_time_of_day() {
+        let mut events = vec![
+            event("1pm", "2pm"),
+            event("12:30pm", "1pm"),
+            event("9:00", "10:00"),
+            event("12:05pm", "1pm"),
+            event("noon", "1pm"),
+            event("12:00am", "1am"),
+            event("9am", "9:30am"),
+            event("23:15", "23:45"),
+        ];
+        sort_events(&mut events);
+        let order: Vec<&str> = events.iter().map(|e| e.description.as_str()).collect();
+        assert_eq!(order, [
+            "12:00am - 1am",
+            "9am - 9:30am",
+            "9:00 - 10:00",
+            "noon - 1pm",
+            "12:05pm - 1pm",
+            "12:30pm - 1pm",
+            "1pm - 2pm",
+            "23:15 - 23:45",
+        ]);
+    }
+
+    #[test]
+    fn event_times_read_old_and_new_forms() {
+        let line = |start: &str, end: &str| format!(r#"{{"id":1,"description":"gym","start":"{}","end":"{}","repeat":"monday"}}"#, start, end);
+        let cases = [
+            (line("9:30am", "12:00pm"), Some(("09:30", "12:00"))),
+            (line("12:15AM", "1:00am"), Some(("00:15", "01:00"))),
+            (line("17:30", "18:45"), Some(("17:30", "18:45"))),
+            (line("9:30", "25:00"), None),
+            (line("soon", "later"), None),
+        ];
+        for (input, expected) in cases {
+            let event = serde_json::from_str::<Event1>(&input).ok();
+            let found = event.as_ref().map(|e| (e.start.format("%H:%M").to_string(), e.end.format("%H:%M").to_string()));
+            assert_eq!(found.as_ref().map(|(s, e)| (s.as_str(), e.as_str())), expected, "{}", input);
+            //written back in 24 hour form
+            if let Some(event) = event {
+                let written = serde_json::to_value(&event).unwrap();
+                assert_eq!(written["start"], expected.unwrap().0);
+            }
+        }
+    }
+
+    #[test]
+    fn events_end_after_they_start() {
+        assert!(check_event_times(&event("9am", "10am")).is_ok());
+        assert!(check_event_times(&event("10am", "10am")).is_err());
+        assert!(check_event_times(&event("1pm", "noon")).is_err());
+    }
+}
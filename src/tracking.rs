@@ -0,0 +1,275 @@
+//time tracking. `vayu start` opens an interval on a task and `vayu stop` closes it.
+//only one task is tracked at a time, so starting another stops the current one.
+
+use crate::clock;
+use crate::store::{self, Interval, Task};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, SecondsFormat, TimeZone};
+use crossterm::style::Stylize;
+use std::collections::BTreeMap;
+
+pub fn start(tasks: &mut [Task], key: &str) {
+    let index = match store::find_task(tasks, key) {
+        Ok(index) => index,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+    if tasks[index].done {
+        println!("task {} is already done", tasks[index].id);
+        return;
+    }
+    if tasks[index].is_active() {
+        println!("task {} is already started", tasks[index].id);
+        return;
+    }
+    let now = clock::now();
+    stop_active(tasks, now);
+    let task = &mut tasks[index];
+    task.intervals.push(Interval { start: now, end: None });
+    println!("started task {} ({})", task.id, task.description);
+}
+
+pub fn stop(tasks: &mut [Task]) {
+    if !stop_active(tasks, clock::now()) {
+        println!("no task is started");
+    }
+}
+
+//closes the running interval, if there is one. returns whether anything was stopped
+pub fn stop_active(tasks: &mut [Task], now: DateTime<Local>) -> bool {
+    let mut stopped = false;
+    for task in tasks.iter_mut().filter(|task| task.is_active()) {
+        if let Some(interval) = task.intervals.last_mut() {
+            interval.end = Some(now);
+            println!("stopped task {} after {}", task.id, hours_minutes(now - interval.start));
+            stopped = true;
+        }
+    }
+    stopped
+}
+
+//time tracked on a task so far, counting a running interval up to now
+pub fn tracked(task: &Task, now: DateTime<Local>) -> Duration {
+    task.intervals.iter().map(|interval| interval.end.unwrap_or(now) - interval.start).fold(Duration::zero(), |a, b| a + b)
+}
+
+pub fn hours_minutes(duration: Duration) -> String {
+    format!("{}:{:02}", duration.num_hours(), duration.num_minutes() % 60)
+}
+
+fn hours(duration: Duration) -> String {
+    format!("{:.2}", duration.num_seconds() as f64 / 3600.0)
+}
+
+//one piece of an interval that falls within a single day
+struct Piece<'a> {
+    task: &'a Task,
+    day: NaiveDate,
+    start: DateTime<Local>,
+    end: DateTime<Local>,
+}
+
+//splits every interval at midnight and keeps the parts between `from` and `to`
+fn pieces(tasks: &[Task], from: Option<DateTime<Local>>, to: DateTime<Local>) -> Vec<Piece<'_>> {
+    let mut pieces = Vec::new();
+    for task in tasks {
+        for interval in &task.intervals {
+            let mut start = from.map_or(interval.start, |from| interval.start.max(from));
+            let end = interval.end.unwrap_or(to).min(to);
+            while start < end {
+                let midnight = start.date_naive().succ_opt()
+                    .and_then(|day| Local.from_local_datetime(&day.and_hms_opt(0, 0, 0)?).earliest())
+                    .unwrap_or(end);
+                let piece_end = midnight.min(end);
+                pieces.push(Piece { task, day: start.date_naive(), start, end: piece_end });
+                start = piece_end;
+            }
+        }
+    }
+    pieces.sort_by_key(|piece| piece.start);
+    pieces
+}
+
+//totals by task, tag and day, or every piece of work as csv. `week` limits it to the current week
+pub fn report(tasks: &[Task], week: bool, csv: bool) {
+    let now = clock::now();
+    let from = if week {
+        let monday = now.date_naive() - Duration::days(now.weekday().num_days_from_monday() as i64);
+        monday.and_hms_opt(0, 0, 0).and_then(|start| Local.from_local_datetime(&start).earliest())
+    }
+    else {
+        None
+    };
+    let pieces = pieces(tasks, from, now);
+    if csv {
+        println!("date,task,uuid,description,tags,start,end,hours");
+        for piece in &pieces {
+            let row = [
+                piece.day.format("%Y-%m-%d").to_string(),
+                piece.task.id.to_string(),
+                piece.task.uuid.to_string(),
+                piece.task.description.clone(),
+                piece.task.tags.join(" "),
+                piece.start.to_rfc3339_opts(SecondsFormat::Secs, false),
+                piece.end.to_rfc3339_opts(SecondsFormat::Secs, false),
+                hours(piece.end - piece.start),
+            ];
+            let row: Vec<String> = row.iter().map(|field| csv_field(field)).collect();
+            println!("{}", row.join(","));
+        }
+        return;
+    }
+    if pieces.is_empty() {
+        println!("no time tracked{}", if week { " this week" } else { "" });
+        return;
+    }
+    let totals = totals(&pieces);
+    match from {
+        Some(from) => println!("time tracked from {} to {}", from.format("%Y-%m-%d"), now.format("%Y-%m-%d")),
+        None => println!("all time tracked"),
+    }
+    println!();
+    println!("ID  | Hours  | Task");
+    println!("----|--------|-----");
+    for (id, (task, length)) in &totals.by_task {
+        println!("{}| {:>6} | {}", format!("{:<4}", id).green(), hours(*length), task.description);
+    }
+    println!();
+    println!("Tag              | Hours");
+    println!("-----------------|-------");
+    for (tag, length) in &totals.by_tag {
+        println!("{:<16} | {:>6}", tag, hours(*length));
+    }
+    println!();
+    println!("Day            | Hours");
+    println!("---------------|-------");
+    for (day, length) in &totals.by_day {
+        println!("{} {} | {:>6}", day.format("%a"), day.format("%Y-%m-%d"), hours(*length));
+    }
+    println!();
+    println!("total: {} hours", hours(totals.total));
+}
+
+//time adding up to the report's tables
+struct Totals<'a> {
+    by_task: BTreeMap<i32, (&'a Task, Duration)>,
+    by_tag: BTreeMap<String, Duration>,
+    by_day: BTreeMap<NaiveDate, Duration>,
+    total: Duration,
+}
+
+fn totals<'a>(pieces: &[Piece<'a>]) -> Totals<'a> {
+    let mut totals = Totals { by_task: BTreeMap::new(), by_tag: BTreeMap::new(), by_day: BTreeMap::new(), total: Duration::zero() };
+    for piece in pieces {
+        let length = piece.end - piece.start;
+        let entry = totals.by_task.entry(piece.task.id).or_insert((piece.task, Duration::zero()));
+        entry.1 += length;
+        //a task with several tags counts towards each of them
+        if piece.task.tags.is_empty() {
+            add(&mut totals.by_tag, "(no tag)".to_string(), length);
+        }
+        for tag in &piece.task.tags {
+            add(&mut totals.by_tag, format!("+{}", tag), length);
+        }
+        add(&mut totals.by_day, piece.day, length);
+        totals.total += length;
+    }
+    totals
+}
+
+fn add<K: Ord>(totals: &mut BTreeMap<K, Duration>, key: K, length: Duration) {
+    *totals.entry(key).or_insert(Duration::zero()) += length;
+}
+
+//quotes a field when it holds a comma, quote or newline
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    }
+    else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(text: &str) -> DateTime<Local> {
+        clock::parse(text).unwrap()
+    }
+
+    fn task(id: i32, tags: &[&str], intervals: &[(&str, Option<&str>)]) -> Task {
+        let mut task = Task::new(id, format!("task {}", id), String::new());
+        task.tags = tags.iter().map(|tag| tag.to_string()).collect();
+        task.intervals = intervals.iter().map(|(start, end)| Interval { start: at(start), end: end.map(at) }).collect();
+        task
+    }
+
+    //1 was worked on over midnight, 2 is still running
+    fn tasks() -> Vec<Task> {
+        vec![
+            task(1, &["infra", "ops"], &[("2024-06-10 22:00", Some("2024-06-11 02:00"))]),
+            task(2, &[], &[("2024-06-12 08:00", None)]),
+        ]
+    }
+
+    #[test]
+    fn splits_at_midnight() {
+        let tasks = tasks();
+        //(task, day, minutes) for each piece
+        let cases = [
+            (None, vec![(1, "2024-06-10", 120), (1, "2024-06-11", 120), (2, "2024-06-12", 60)]),
+            (Some("2024-06-11 01:00"), vec![(1, "2024-06-11", 60), (2, "2024-06-12", 60)]),
+            (Some("2024-06-12 08:30"), vec![(2, "2024-06-12", 30)]),
+            (Some("2024-06-12 10:00"), vec![]),
+        ];
+        for (from, expected) in cases {
+            let found: Vec<(i32, String, i64)> = pieces(&tasks, from.map(at), at("2024-06-12 09:00")).iter()
+                .map(|piece| (piece.task.id, piece.day.to_string(), (piece.end - piece.start).num_minutes()))
+                .collect();
+            let expected: Vec<(i32, String, i64)> = expected.into_iter().map(|(id, day, minutes)| (id, day.to_string(), minutes)).collect();
+            assert_eq!(found, expected, "from {:?}", from);
+        }
+    }
+
+    #[test]
+    fn adds_up_totals() {
+        let tasks = tasks();
+        let pieces = pieces(&tasks, None, at("2024-06-12 09:00"));
+        let totals = totals(&pieces);
+        let minutes = |length: &Duration| length.num_minutes();
+        let by_task: Vec<(i32, i64)> = totals.by_task.iter().map(|(id, (_, length))| (*id, minutes(length))).collect();
+        assert_eq!(by_task, [(1, 240), (2, 60)]);
+        //a task with two tags counts towards both
+        let by_tag: Vec<(&str, i64)> = totals.by_tag.iter().map(|(tag, length)| (tag.as_str(), minutes(length))).collect();
+        assert_eq!(by_tag, [("(no tag)", 60), ("+infra", 240), ("+ops", 240)]);
+        let by_day: Vec<(String, i64)> = totals.by_day.iter().map(|(day, length)| (day.to_string(), minutes(length))).collect();
+        assert_eq!(by_day, [("2024-06-10".to_string(), 120), ("2024-06-11".to_string(), 120), ("2024-06-12".to_string(), 60)]);
+        assert_eq!(minutes(&totals.total), 300);
+        assert_eq!(tracked(&tasks[1], at("2024-06-12 09:45")).num_minutes(), 105);
+    }
+
+    #[test]
+    fn formats_durations() {
+        let cases = [(0, "0:00", "0.00"), (45, "0:45", "0.75"), (90, "1:30", "1.50"), (25 * 60 + 5, "25:05", "25.08")];
+        for (minutes, clock_time, decimal) in cases {
+            let length = Duration::minutes(minutes);
+            assert_eq!((hours_minutes(length).as_str(), hours(length).as_str()), (clock_time, decimal), "{} minutes", minutes);
+        }
+    }
+
+    #[test]
+    fn quotes_csv_fields() {
+        let cases = [
+            ("plain", "plain"),
+            ("a, b", "\"a, b\""),
+            ("say \"hi\"", "\"say \"\"hi\"\"\""),
+            ("two\nlines", "\"two\nlines\""),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(csv_field(input), expected, "{:?}", input);
+        }
+    }
+}
@@ -0,0 +1,166 @@
+//subtasks. a task points at its parent by id and the tree is worked out from that when it's shown.
+
+use crate::store::Task;
+use std::collections::HashSet;
+
+//direct subtasks of a task, done or not
+pub fn children(tasks: &[Task], id: i32) -> impl Iterator<Item = &Task> {
+    tasks.iter().filter(move |task| task.parent == Some(id))
+}
+
+//(completed, total) subtasks, or None if the task has none
+pub fn progress(tasks: &[Task], id: i32) -> Option<(usize, usize)> {
+    let total = children(tasks, id).count();
+    if total == 0 {
+        return None;
+    }
+    Some((children(tasks, id).filter(|task| task.done).count(), total))
+}
+
+//ids of every pending task below this one, children first
+pub fn open_descendants(tasks: &[Task], id: i32) -> Vec<i32> {
+    let mut found = Vec::new();
+    let mut seen = HashSet::from([id]);
+    let mut queue = vec![id];
+    while let Some(parent) = queue.pop() {
+        for child in children(tasks, parent) {
+            //a hand edited store could have a loop, so never visit a task twice
+            if !seen.insert(child.id) {
+                continue;
+            }
+            if !child.done {
+                found.push(child.id);
+            }
+            queue.push(child.id);
+        }
+    }
+    found
+}
+
+//puts the shown tasks (indexes into `tasks`, already sorted) into tree order with each task's depth.
+//subtasks follow their parent in the same order they were given. a task whose parent isn't shown is a root.
+pub fn order(tasks: &[Task], shown: &[usize]) -> Vec<(usize, usize)> {
+    let shown_ids: HashSet<i32> = shown.iter().map(|&i| tasks[i].id).collect();
+    let mut ordered = Vec::new();
+    let mut placed = HashSet::new();
+    for &i in shown {
+        let is_root = match tasks[i].parent {
+            Some(parent) => !shown_ids.contains(&parent),
+            None => true,
+        };
+        if is_root {
+            place(tasks, shown, i, 0, &mut ordered, &mut placed);
+        }
+    }
+    //tasks caught in a parent loop have no root, show them at the top level rather than not at all
+    for &i in shown {
+        if !placed.contains(&i) {
+            place(tasks, shown, i, 0, &mut ordered, &mut placed);
+        }
+    }
+    ordered
+}
+
+fn place(tasks: &[Task], shown: &[usize], i: usize, depth: usize, ordered: &mut Vec<(usize, usize)>, placed: &mut HashSet<usize>) {
+    if !placed.insert(i) {
+        return;
+    }
+    ordered.push((i, depth));
+    for &child in shown {
+        if tasks[child].parent == Some(tasks[i].id) {
+            place(tasks, shown, child, depth + 1, ordered, placed);
+        }
+    }
+}
+
+//text shown in front of a subtask's description
+pub fn indent(depth: usize) -> String {
+    if depth == 0 {
+        String::new()
+    }
+    else {
+        format!("{}└ ", "  ".repeat(depth - 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(id: i32, parent: Option<i32>, done: bool) -> Task {
+        let mut task = Task::new(id, format!("task {}", id), String::new());
+        task.parent = parent;
+        task.done = done;
+        task
+    }
+
+    //1 has subtasks 2 and 5 (done), 2 has 3 (done) and 4, 5 has 9. 6 stands alone and 7 and 8 are each other's parent
+    fn tasks() -> Vec<Task> {
+        vec![
+            task(1, None, false),
+            task(2, Some(1), false),
+            task(3, Some(2), true),
+            task(4, Some(2), false),
+            task(5, Some(1), true),
+            task(6, None, false),
+            task(7, Some(8), false),
+            task(8, Some(7), false),
+            task(9, Some(5), false),
+        ]
+    }
+
+    #[test]
+    fn counts_progress() {
+        let tasks = tasks();
+        let cases = [(1, Some((1, 2))), (2, Some((1, 2))), (5, Some((0, 1))), (6, None), (4, None)];
+        for (id, expected) in cases {
+            assert_eq!(progress(&tasks, id), expected, "task {}", id);
+        }
+    }
+
+    #[test]
+    fn finds_open_descendants() {
+        let tasks = tasks();
+        let cases: [(i32, &[i32]); 5] = [
+            (1, &[2, 4, 9]),
+            (2, &[4]),
+            (6, &[]),
+            //a done task's open subtasks still count
+            (5, &[9]),
+            (7, &[8]),
+        ];
+        for (id, expected) in cases {
+            let mut found = open_descendants(&tasks, id);
+            found.sort();
+            assert_eq!(found, expected, "task {}", id);
+        }
+    }
+
+    #[test]
+    fn orders_tree() {
+        let tasks = tasks();
+        //shown tasks and the order they come out in, as (id, depth)
+        let cases = [
+            (vec![1, 2, 3, 4, 5, 6, 9], vec![(1, 0), (2, 1), (3, 2), (4, 2), (5, 1), (9, 2), (6, 0)]),
+            //subtasks keep the order they were given in
+            (vec![4, 3, 2, 1], vec![(1, 0), (2, 1), (4, 2), (3, 2)]),
+            //a parent that isn't shown leaves its subtasks at the top
+            (vec![3, 6, 4], vec![(3, 0), (6, 0), (4, 0)]),
+            (vec![9, 5], vec![(5, 0), (9, 1)]),
+            //a parent loop has no root, so the first of it shown goes at the top
+            (vec![8, 7], vec![(8, 0), (7, 1)]),
+        ];
+        for (ids, expected) in cases {
+            let shown: Vec<usize> = ids.iter().map(|id| tasks.iter().position(|t| t.id == *id).unwrap()).collect();
+            let ordered: Vec<(i32, usize)> = order(&tasks, &shown).iter().map(|&(i, depth)| (tasks[i].id, depth)).collect();
+            assert_eq!(ordered, expected, "{:?}", ids);
+        }
+    }
+
+    #[test]
+    fn indents() {
+        for (depth, expected) in [(0, ""), (1, "└ "), (3, "    └ ")] {
+            assert_eq!(indent(depth), expected, "depth {}", depth);
+        }
+    }
+}
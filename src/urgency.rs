@@ -0,0 +1,138 @@
+//urgency score used to order tasks by what to work on next.
+//each part is a coefficient times a factor between 0 and 1, so the weights are easy to compare.
+
+use crate::deps;
+use crate::store::Task;
+use chrono::{DateTime, Local, NaiveDate};
+use std::collections::HashMap;
+
+const PRIORITY_WEIGHT: f64 = 6.0;
+const DUE_WEIGHT: f64 = 12.0;
+const AGE_WEIGHT: f64 = 2.0;
+//a blocked task can't be started yet, and finishing a blocking one frees up others
+const BLOCKED_WEIGHT: f64 = -5.0;
+const BLOCKING_WEIGHT: f64 = 8.0;
+//work that was planned to have started by now
+const SCHEDULED_WEIGHT: f64 = 5.0;
+
+//tasks reach full age urgency after a year
+const AGE_MAX_DAYS: f64 = 365.0;
+
+//`tasks` is the whole list, used to see what the task is waiting on and what waits on it
+pub fn urgency(tasks: &[Task], task: &Task, now: DateTime<Local>) -> f64 {
+    PRIORITY_WEIGHT * priority_factor(task.priority)
+        + DUE_WEIGHT * due_factor(&task.due, now)
+        + AGE_WEIGHT * age_factor(task.created_at, now)
+        + BLOCKED_WEIGHT * flag(deps::is_blocked(tasks, task))
+        + BLOCKING_WEIGHT * flag(deps::is_blocking(tasks, task))
+        + SCHEDULED_WEIGHT * flag(task.scheduled.as_deref().is_some_and(|s| s <= now.format("%Y-%m-%d").to_string().as_str()))
+}
+
+fn flag(set: bool) -> f64 {
+    if set { 1.0 } else { 0.0 }
+}
+
+fn priority_factor(priority: Option<u8>) -> f64 {
+    match priority {
+        Some(1) => 1.0,
+        Some(2) => 0.65,
+        Some(3) => 0.3,
+        _ => 0.0,
+    }
+}
+
+//ramps from 0.2 two weeks out up to 1.0 once the task is a week overdue
+fn due_factor(due: &str, now: DateTime<Local>) -> f64 {
+    let due = match NaiveDate::parse_from_str(due, "%Y-%m-%d") {
+        Ok(due) => due,
+        Err(_) => return 0.0,
+    };
+    let days_left = (due - now.date_naive()).num_days() as f64;
+    if days_left <= -7.0 {
+        1.0
+    }
+    else if days_left >= 14.0 {
+        0.2
+    }
+    else {
+        (14.0 - days_left) * 0.8 / 21.0 + 0.2
+    }
+}
+
+fn age_factor(created_at: Option<DateTime<Local>>, now: DateTime<Local>) -> f64 {
+    match created_at {
+        Some(created_at) => ((now - created_at).num_days() as f64 / AGE_MAX_DAYS).clamp(0.0, 1.0),
+        None => 0.0,
+    }
+}
+
+//highest urgency first. ties fall back to the due date
+pub fn sort_by_urgency(tasks: &mut [Task], now: DateTime<Local>) {
+    let scores: HashMap<i32, f64> = tasks.iter().map(|task| (task.id, urgency(tasks, task, now))).collect();
+    tasks.sort_by(|t1, t2| {
+        scores[&t2.id]
+            .total_cmp(&scores[&t1.id])
+            .then_with(|| t1.due.cmp(&t2.due))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock;
+
+    //what a case sets on a fresh task
+    type Change = fn(&mut Task);
+
+    fn now() -> DateTime<Local> {
+        clock::parse("2024-06-12 09:00").unwrap()
+    }
+
+    fn task(id: i32, due: &str) -> Task {
+        let mut task = Task::new(id, format!("task {}", id), due.to_string());
+        task.created_at = Some(now());
+        task
+    }
+
+    #[test]
+    fn scores() {
+        let cases: [(&str, Change, f64); 15] = [
+            ("nothing set", |_| {}, 0.0),
+            ("priority H", |t| t.priority = Some(1), 6.0),
+            ("priority M", |t| t.priority = Some(2), 3.9),
+            ("priority L", |t| t.priority = Some(3), 1.8),
+            ("priority 4", |t| t.priority = Some(4), 0.0),
+            ("due today", |t| t.due = "2024-06-12".to_string(), 8.8),
+            ("due in a week", |t| t.due = "2024-06-19".to_string(), 5.6),
+            ("due in over two weeks", |t| t.due = "2024-07-30".to_string(), 2.4),
+            ("a week overdue", |t| t.due = "2024-06-01".to_string(), 12.0),
+            ("a year old", |t| t.created_at = clock::parse("2023-01-01 09:00").ok(), 2.0),
+            ("three months old", |t| t.created_at = clock::parse("2024-03-14 09:00").ok(), 0.49),
+            ("scheduled today", |t| t.scheduled = Some("2024-06-12".to_string()), 5.0),
+            ("scheduled tomorrow", |t| t.scheduled = Some("2024-06-13".to_string()), 0.0),
+            //2 is pending, and 3 waits on 9
+            ("blocked", |t| t.depends = vec![2], -5.0),
+            ("blocking", |t| t.id = 9, 8.0),
+        ];
+        let mut waiting = task(3, "");
+        waiting.depends = vec![9];
+        for (name, change, expected) in cases {
+            let mut scored = task(1, "");
+            change(&mut scored);
+            let tasks = vec![scored.clone(), task(2, ""), waiting.clone()];
+            let score = urgency(&tasks, &scored, now());
+            assert_eq!((score * 100.0).round() / 100.0, expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn sorts_most_urgent_first() {
+        let mut tasks = vec![task(1, "2024-06-30"), task(2, "2024-06-20"), task(3, "2024-06-12"), task(4, ""), task(5, "2024-06-30")];
+        tasks[3].priority = Some(1);
+        //5 waits on 1, so 1 goes ahead of the task due on the same day
+        tasks[4].depends = vec![1];
+        sort_by_urgency(&mut tasks, now());
+        let ids: Vec<i32> = tasks.iter().map(|task| task.id).collect();
+        assert_eq!(ids, [1, 3, 4, 2, 5]);
+    }
+}
@@ -0,0 +1,142 @@
+//workflow states a task moves through, ex: todo -> doing -> review -> done (set in config.toml).
+//a task starts in the first state and the last one means done, so `done` and `completed_at` follow it.
+//every move is timestamped in the task's transitions.
+
+use crate::config;
+use crate::recur;
+use crate::store::{Task, Transition};
+use chrono::{DateTime, Local};
+
+pub fn first_state() -> &'static str {
+    &config::states()[0]
+}
+
+pub fn done_state() -> &'static str {
+    config::states().last().map(|s| s.as_str()).unwrap_or("done")
+}
+
+//the state a task is in. tasks from before the workflow, or in a state that was taken out of
+//config.toml, are in the first state until they are done
+pub fn state(task: &Task) -> &str {
+    if task.done {
+        return done_state();
+    }
+    let states = config::states();
+    match task.state.as_deref() {
+        Some(state) if states[..states.len() - 1].iter().any(|s| s == state) => state,
+        _ => first_state(),
+    }
+}
+
+//a state as typed, in any case
+pub fn parse(name: &str) -> Result<&'static str, String> {
+    config::states().iter()
+        .find(|state| state.eq_ignore_ascii_case(name.trim()))
+        .map(|state| state.as_str())
+        .ok_or_else(|| format!("unknown state \"{}\". use {}", name.trim(), config::states().join(", ")))
+}
+
+//moves a task to `state`. entering the last state completes it and leaving it reopens it
+pub fn enter(task: &mut Task, state: &str, now: DateTime<Local>) {
+    task.done = state == done_state();
+    task.completed_at = if task.done { Some(now) } else { None };
+    task.state = Some(state.to_string());
+    task.transitions.push(Transition { state: state.to_string(), at: now });
+}
+
+//marks the tasks done, stopping their timers. recurring tasks get their next instance added with
+//ids from `next_id` on. returns what was done, a line for each change
+pub fn complete(tasks: &mut Vec<Task>, mut next_id: i32, ids: &[i32], now: DateTime<Local>) -> Vec<String> {
+    let mut messages = Vec::new();
+    let mut instances = Vec::new();
+    for task in tasks.iter_mut().filter(|t| ids.contains(&t.id)) {
+        //a finished task stops being tracked
+        if let Some(interval) = task.intervals.last_mut().filter(|interval| interval.end.is_none()) {
+            interval.end = Some(now);
+        }
+        enter(task, done_state(), now);
+        messages.push(format!("task {} done", task.id));
+        if let Some(instance) = recur::next_instance(task, next_id, now.date_naive()) {
+            let every = instance.recur.as_deref().and_then(|rule| recur::parse(rule).ok()).map(|rule| recur::describe(&rule)).unwrap_or_default();
+            messages.push(format!("next one is task {} due {} ({})", instance.id, instance.due, every));
+            instances.push(instance);
+            next_id += 1;
+        }
+    }
+    tasks.extend(instances);
+    messages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock;
+    use crate::store::Interval;
+
+    //no config.toml is loaded, so these run with the default workflow, todo -> doing -> done
+    fn task(id: i32, state: Option<&str>, done: bool) -> Task {
+        let mut task = Task::new(id, format!("task {}", id), "2024-06-12".to_string());
+        task.state = state.map(String::from);
+        task.done = done;
+        task
+    }
+
+    #[test]
+    fn reads_states() {
+        let cases = [
+            (None, false, "todo"),
+            (Some("doing"), false, "doing"),
+            (Some("doing"), true, "done"),
+            (None, true, "done"),
+            //a state taken out of config.toml, or done on a task that isn't
+            (Some("review"), false, "todo"),
+            (Some("done"), false, "todo"),
+        ];
+        for (stored, done, expected) in cases {
+            assert_eq!(state(&task(1, stored, done)), expected, "{:?} done:{}", stored, done);
+        }
+    }
+
+    #[test]
+    fn parses_states() {
+        let cases = [
+            ("doing", Ok("doing")),
+            (" DONE ", Ok("done")),
+            ("review", Err("unknown state \"review\". use todo, doing, done")),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(parse(input), expected.map_err(String::from), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn moves_between_states() {
+        let now = clock::parse("2024-06-12 09:00").unwrap();
+        let mut task = task(1, None, false);
+        //the state entered and whether the task is done after it
+        let cases = [("doing", false), ("done", true), ("doing", false), ("todo", false)];
+        for (i, (next, done)) in cases.into_iter().enumerate() {
+            enter(&mut task, next, now);
+            assert_eq!((state(&task), task.done, task.completed_at.is_some()), (next, done, done), "into {}", next);
+            assert_eq!(task.transitions.len(), i + 1);
+        }
+        let moves: Vec<&str> = task.transitions.iter().map(|t| t.state.as_str()).collect();
+        assert_eq!(moves, ["doing", "done", "doing", "todo"]);
+    }
+
+    #[test]
+    fn completes_tasks() {
+        let now = clock::parse("2024-06-12 09:00").unwrap();
+        let mut tracked = task(1, Some("doing"), false);
+        tracked.intervals.push(Interval { start: clock::parse("2024-06-12 08:00").unwrap(), end: None });
+        let mut weekly = task(2, None, false);
+        weekly.recur = Some("weekly".to_string());
+        let mut tasks = vec![tracked, weekly, task(3, None, false)];
+
+        let messages = complete(&mut tasks, 10, &[1, 2], now);
+        assert_eq!(messages, ["task 1 done", "task 2 done", "next one is task 10 due 2024-06-19 (every week)"]);
+        assert_eq!(tasks[0].intervals[0].end, Some(now));
+        let states: Vec<(i32, &str)> = tasks.iter().map(|task| (task.id, state(task))).collect();
+        assert_eq!(states, [(1, "done"), (2, "done"), (3, "todo"), (10, "todo")]);
+    }
+}